@@ -293,6 +293,8 @@ pub fn two_phase_svd_verif<F: ScalarField>(
     // theoretical analysis indicates this can be as small as 1e-13
     const EPS_U: f64 = 1e-10;
     const MAX_NORM: f64 = 100.0;
+    // the Freivalds checks fail to catch a wrong product with probability at most 2^-SOUNDNESS_BITS
+    const SOUNDNESS_BITS: u32 = 128;
     // for PRECISION_BITS = 42, size*MAX_NORM*2^-(P+1) and MAX_NORM*EPS_U are both almost 1e-8
     // NOTE: if you decrease PRECISION_BITS, you should also increase the error value in line 46 of input-creator.py
     const PRECISION_BITS: u32 = 42;
@@ -325,12 +327,16 @@ pub fn two_phase_svd_verif<F: ScalarField>(
     let max_dim = cmp::max(m.num_rows, m.num_col);
 
     let (err_svd, err_u) = err_calc(PRECISION_BITS, max_dim, MAX_NORM, EPS_SVD, EPS_U);
+    // degree of the batched Freivalds check in `check_svd_phase1_amplified`
+    let freivalds_degree = max_dim + 2 * m.num_rows + m.num_col - 2;
+    let num_reps = freivalds_num_reps::<F>(SOUNDNESS_BITS, freivalds_degree);
 
     let _chip = RlpChip::new(&range, None);
     // let witness = chip.decompose_rlp_field_phase0(ctx, inputs, max_len);
 
     let (u_t, v_t, m_times_vt, u_times_ut, v_times_vt) =
         check_svd_phase0(ctx, &fpchip, &m, &u, &v, &d, err_svd, err_u, 30);
+    check_svd_norm_bound(ctx, &fpchip, &d, max_dim, err_svd, err_u);
 
     // copied from rlp_string_circuit in axiom-eth> src> rlp> tests
     let synthesize_phase1 = move |b: &mut RlcThreadBuilder<F>, rlc: &RlcChip<F>| {
@@ -347,7 +353,7 @@ pub fn two_phase_svd_verif<F: ScalarField>(
         let init_rand = rlc.gamma_pow_cached()[0];
         println!("The init rand = {:?}", init_rand.value());
 
-        check_svd_phase1(
+        check_svd_phase1_amplified(
             ctx_gate,
             &fpchip2,
             &m,
@@ -359,6 +365,7 @@ pub fn two_phase_svd_verif<F: ScalarField>(
             &u_times_ut,
             &v_times_vt,
             &init_rand,
+            num_reps,
         );
     };
     let circuit = RlpCircuitBuilder::new(builder, None, synthesize_phase1);
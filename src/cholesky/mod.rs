@@ -0,0 +1,255 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+
+/// Given a symmetric matrix `a` (`N X N` dimension), a public margin `delta` and a lower triangular matrix `l` (`N X N` dimension)
+/// in fixed point representation with `fpchip`, performs the first part of checks that `a - delta*Id = l*l^T` is the Cholesky
+/// decomposition of `a - delta*Id`
+///
+/// Constrains `a` to be symmetric, `l` to be lower triangular with strictly positive diagonal entries (at least `2^-PRECISION_BITS`),
+/// and `|(a - delta*Id)[i][j] - (l*l^T)[i][j]| < err_chol` for each `i,j` (see `cholesky_err_calc`)
+///
+/// The entrywise error has operator norm at most `N*err_chol` and `l*l^T` is positive semidefinite, so
+/// `a >= (delta - N*err_chol)*Id`; the quantized `delta` must satisfy `delta > N*err_chol` (asserted outside the circuit), which proves that
+/// `a` is positive definite with smallest eigenvalue at least `delta - N*err_chol`. The honest prover must decompose `a - delta*Id`,
+/// so `delta` should be chosen below the smallest eigenvalue of `a`
+///
+/// `max_bits_l` bounds the entries of `l`: they are constrained to satisfy `|l[i][j]| < 2^max_bits_l`;
+/// it is used to make sure no overflows occur while multiplying
+///
+/// Must call `check_cholesky_phase1` function following this function in the second phase to complete the check
+///
+/// The outputs are simply witnesses to be used for the corresponding variables in `check_cholesky_phase1`
+///
+/// NOTE: the fixed point chip does not check for overflows, so one needs to place some bound on $\Vert a \Vert_2$,
+/// which is assumed to be enforced by the function or program calling this library
+pub fn check_cholesky_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    l: &ZkMatrix<F, PRECISION_BITS>,
+    delta: f64,
+    err_chol: f64,
+    max_bits_l: u32,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>) {
+    // the circuit subtracts the quantized delta, so the bound must hold for the value it represents
+    let delta_q = fpchip.quantization(delta);
+    assert!(fpchip.dequantization(delta_q) > (a.num_rows as f64) * err_chol);
+
+    return check_shifted_cholesky(ctx, fpchip, a, l, delta_q, err_chol, max_bits_l);
+}
+
+/// Given a symmetric matrix `a` (`N X N` dimension) and a lower triangular matrix `l` (`N X N` dimension) in fixed point
+/// representation with `fpchip`, performs the first part of checks that `a = l*l^T` is the Cholesky decomposition of `a`
+///
+/// Same as `check_cholesky_phase0` with no shift: constrains `|a[i][j] - (l*l^T)[i][j]| < err_chol` for each `i,j`, so `l`
+/// can be used with `cholesky_determinant` to compute `det(a)`. Since there is no margin, this does not prove on its own
+/// that `a` is positive definite
+///
+/// Must call `check_cholesky_phase1` function following this function in the second phase to complete the check
+pub fn check_cholesky_factor_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    l: &ZkMatrix<F, PRECISION_BITS>,
+    err_chol: f64,
+    max_bits_l: u32,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>) {
+    return check_shifted_cholesky(ctx, fpchip, a, l, F::zero(), err_chol, max_bits_l);
+}
+
+/// Constraints shared by `check_cholesky_phase0` and `check_cholesky_factor_phase0`, for `a - delta*Id = l*l^T`
+/// with the quantized shift `delta_q`
+fn check_shifted_cholesky<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    l: &ZkMatrix<F, PRECISION_BITS>,
+    delta_q: F,
+    err_chol: f64,
+    max_bits_l: u32,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>) {
+    assert_eq!(a.num_rows, a.num_col);
+    assert_eq!(l.num_rows, l.num_col);
+    assert_eq!(a.num_rows, l.num_rows);
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    check_mat_symmetric(ctx, &a.matrix);
+    check_lower_triangular(ctx, gate, &l.matrix);
+
+    // check that the entries of l correspond to real numbers in (-2^max_bits_l, 2^max_bits_l)
+    let l_bnd_q = BigUint::from(2u32).pow(max_bits_l + PRECISION_BITS);
+    check_mat_entries_bounded(ctx, &range, &l.matrix, &l_bnd_q);
+
+    // diagonal entries of l are in [1, 2^(max_bits_l + PRECISION_BITS)] when quantized
+    for i in 0..l.num_rows {
+        let diag_minus_one = gate.sub(ctx, l.matrix[i][i], Constant(F::one()));
+        range.range_check(ctx, diag_minus_one, (max_bits_l + PRECISION_BITS) as usize);
+    }
+
+    let l_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&l);
+
+    // define the doubly scaled error
+    let err_chol_scale =
+        BigUint::from((err_chol * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    // l*l^T is doubly scaled, so a - delta*Id is scaled once more before comparing
+    let l_times_lt = honest_prover_mat_mul(ctx, &l.matrix, &l_t.matrix);
+    let mut a_scaled = field_mat_scalar_mul(ctx, gate, &a.matrix, quant);
+    let delta_scale = delta_q * quant;
+    for i in 0..a_scaled.len() {
+        a_scaled[i][i] = gate.sub(ctx, a_scaled[i][i], Constant(delta_scale));
+    }
+    check_mat_diff(ctx, &range, &l_times_lt, &a_scaled, &err_chol_scale);
+
+    return (l_t, l_times_lt);
+}
+
+/// Second phase function for checking the Cholesky decomposition;
+///
+/// `check_cholesky_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `l` as used in `check_cholesky_phase0` (or `check_cholesky_factor_phase0`) and other inputs correspond to
+/// its outputs
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// The product is checked with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree `2N-2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_cholesky_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    l: &ZkMatrix<F, PRECISION_BITS>,
+    l_t: &ZkMatrix<F, PRECISION_BITS>,
+    l_times_lt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(l, l_t, l_times_lt)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Calculates `err_chol` for `check_cholesky_phase0` from `eps_chol`
+///
+/// `p` is the PRECISION_BITS for the fixed point chip
+///
+/// `size` is the size of the matrices for which the output is to be used
+///
+/// `max_norm` is the maximum operator norm of `a` for which the output is to be used; then $\Vert l \Vert_2 \leq$ `sqrt(max_norm)`
+/// since `0 <= delta` is below the smallest eigenvalue of `a`
+///
+/// `eps_chol` is the entrywise error of `a - delta*Id - l*l^T` for the (floating point) decomposition before quantization
+///
+/// Quantizing `l` changes each entry of `l*l^T` by at most `2*size*sqrt(max_norm)*2^-(p+1)` and quantizing `a` changes its entries
+/// by at most `2^-(p+1)`, up to second order terms
+pub fn cholesky_err_calc(p: u32, size: usize, max_norm: f64, eps_chol: f64) -> f64 {
+    let precision = 2.0_f64.powf(-1.0 * (p as f64 + 1.0));
+    let err_chol =
+        precision * (size as f64) * (2.0 * max_norm.sqrt() + precision) + precision + eps_chol;
+    return err_chol;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+    use halo2_base::gates::builder::GateThreadBuilder;
+
+    const ERR_CHOL: f64 = 1e-8;
+
+    /// Runs both phases of the Cholesky check of `a - delta*Id = l*l^T` and outputs whether `MockProver` accepts
+    fn cholesky_accepts(a: Vec<Vec<f64>>, l: Vec<Vec<f64>>, delta: f64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &a);
+            let l = ZkMatrix::new(ctx, fpchip, &l);
+            let (l_t, l_times_lt) = check_cholesky_phase0(ctx, fpchip, &a, &l, delta, ERR_CHOL, 4);
+            let init_rand = mock_init_rand(ctx);
+            check_cholesky_phase1(ctx, fpchip, &l, &l_t, &l_times_lt, &init_rand, 2);
+        });
+    }
+
+    /// Cholesky factor of `[[3, 2], [2, 4]] = [[4, 2], [2, 5]] - Id`
+    fn spd_factor() -> Vec<Vec<f64>> {
+        let l11 = 3.0_f64.sqrt();
+        let l21 = 2.0 / l11;
+        let l22 = (4.0 - l21 * l21).sqrt();
+        return vec![vec![l11, 0.0], vec![l21, l22]];
+    }
+
+    #[test]
+    fn test_cholesky_honest() {
+        let a = vec![vec![4.0, 2.0], vec![2.0, 5.0]];
+        assert!(cholesky_accepts(a, spd_factor(), 1.0));
+        assert!(cholesky_err_calc(PRECISION_BITS, 2, 6.0, 0.0) < ERR_CHOL);
+    }
+
+    #[test]
+    fn test_cholesky_indefinite() {
+        // eigenvalues 3 and -1; the decomposition of a - 0.5*Id breaks down at the second pivot, which is -7.5
+        let a = vec![vec![1.0, 2.0], vec![2.0, 1.0]];
+        let l11 = 0.5_f64.sqrt();
+        let l = vec![vec![l11, 0.0], vec![2.0 / l11, 1.0]];
+        assert!(!cholesky_accepts(a, l, 0.5));
+    }
+
+    #[test]
+    fn test_cholesky_not_lower_triangular() {
+        // l*r for a rotation r still satisfies (l*r)*(l*r)^T = l*l^T and has a positive diagonal
+        let l = spd_factor();
+        let (c, s) = (0.1_f64.cos(), 0.1_f64.sin());
+        let lr = vec![
+            vec![l[0][0] * c, -l[0][0] * s],
+            vec![l[1][0] * c + l[1][1] * s, -l[1][0] * s + l[1][1] * c],
+        ];
+        let a = vec![vec![4.0, 2.0], vec![2.0, 5.0]];
+        assert!(!cholesky_accepts(a, lr, 1.0));
+    }
+
+    #[test]
+    fn test_cholesky_non_positive_diagonal() {
+        let a = vec![vec![2.0, 1.0], vec![1.0, 2.0]];
+        // a - Id = [[1, 1], [1, 1]] is singular, so its factor has a zero diagonal entry
+        assert!(!cholesky_accepts(a.clone(), vec![vec![1.0, 0.0], vec![1.0, 0.0]], 1.0));
+        // negating a column of the factor of a - 0.5*Id does not change l*l^T
+        let l11 = 1.5_f64.sqrt();
+        let l21 = 1.0 / l11;
+        let l22 = (1.5 - l21 * l21).sqrt();
+        assert!(cholesky_accepts(a.clone(), vec![vec![l11, 0.0], vec![l21, l22]], 0.5));
+        assert!(!cholesky_accepts(a, vec![vec![l11, 0.0], vec![l21, -l22]], 0.5));
+    }
+
+    /// Runs `check_cholesky_phase0` outside of `mock_accepts`, for inputs on which it is expected to panic
+    fn cholesky_phase0_unchecked(delta: f64, err_chol: f64) {
+        let mut builder = GateThreadBuilder::<Fr>::new(false);
+        let ctx = builder.main(0);
+        let fpchip = FixedPointChip::<Fr, PRECISION_BITS>::default(12);
+        let a = ZkMatrix::new(ctx, &fpchip, &vec![vec![4.0, 2.0], vec![2.0, 5.0]]);
+        let l = ZkMatrix::new(ctx, &fpchip, &spd_factor());
+        check_cholesky_phase0(ctx, &fpchip, &a, &l, delta, err_chol, 4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cholesky_delta_at_bound() {
+        // 2^-19 is quantized exactly
+        cholesky_phase0_unchecked(2.0_f64.powi(-19), 2.0_f64.powi(-20));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cholesky_delta_quantized_below_bound() {
+        // 2^-34 > 2*10^-12, but it quantizes to zero
+        cholesky_phase0_unchecked(2.0_f64.powi(-34), 1e-12);
+    }
+}
@@ -0,0 +1,219 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+
+/// Given a permutation `perm` of `{0, 1, ..., N-1}` outputs its sign, i.e., the field element `1` if `perm` has an even
+/// number of inversions and `-1` otherwise
+///
+/// Assumes `perm` is constrained to be a permutation (e.g. the first output of `check_lu_phase0`); the output is *not* fixed
+/// point encoded
+///
+/// Computes the parity of the number of inversions `perm[i] > perm[j]` for `i < j` as an xor of the comparisons;
+/// leads to O(N^2) cells
+pub fn permutation_sign<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    perm: &Vec<AssignedValue<F>>,
+) -> AssignedValue<F> {
+    #![allow(non_snake_case)]
+    let N = perm.len();
+    let gate: &GateChip<F> = &range.gate;
+    // all entries of perm are less than 2^num_bits
+    let num_bits = bit_len(N) as usize;
+
+    let mut parity = ctx.load_zero();
+    for i in 0..N {
+        for j in (i + 1)..N {
+            let inversion = range.is_less_than(ctx, perm[j], perm[i], num_bits);
+            // parity xor inversion = parity + inversion - 2*parity*inversion
+            let both = gate.mul(ctx, parity, inversion);
+            let parity_plus_inv = gate.add(ctx, parity, inversion);
+            parity = gate.mul_add(ctx, both, Constant(-F::from(2)), parity_plus_inv);
+        }
+    }
+    // sign = 1 - 2*parity
+    let sign = gate.mul_add(ctx, parity, Constant(-F::from(2)), Constant(F::one()));
+    return sign;
+}
+
+/// Multiplies the fixed point numbers in `factors` from left to right as `qmul` does, rescaling with `signed_div_scale`
+/// after each multiplication
+///
+/// Constrains every partial product (including the first factor) to satisfy `|x| < 2^max_bits_det`
+/// so that no intermediate product overflows; every product is also constrained to satisfy `|x| < 2^(3*PRECISION_BITS)`
+/// before it is rescaled (as an unscaled field element), which `signed_div_scale` requires
+fn bounded_product<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    factors: &Vec<AssignedValue<F>>,
+    max_bits_det: u32,
+) -> AssignedValue<F> {
+    assert!(factors.len() > 0);
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let bnd_q = BigUint::from(2u32).pow(max_bits_det + PRECISION_BITS);
+    let unscaled_bnd = BigUint::from(2u32).pow(3 * PRECISION_BITS);
+
+    let mut prod = factors[0];
+    check_abs_less_than(ctx, &range, prod, &bnd_q);
+    for factor in &factors[1..] {
+        let prod_s = fpchip.gate().mul(ctx, prod, *factor);
+        check_abs_less_than(ctx, &range, prod_s, &unscaled_bnd);
+        let (prod_rescaled, _) = fpchip.signed_div_scale(ctx, prod_s);
+        prod = prod_rescaled;
+        check_abs_less_than(ctx, &range, prod, &bnd_q);
+    }
+    return prod;
+}
+
+/// Outputs the determinant of `a` in fixed point representation from its LU decomposition `p*a = l*u`,
+/// i.e., `det(a) = sign(perm) * u[0][0] * ... * u[N-1][N-1]`
+///
+/// `u` and `perm` must be the upper triangular factor and the (assigned) permutation verified by `check_lu_phase0`
+/// and `check_lu_phase1`; the unit diagonal of `l` does not contribute to the determinant
+///
+/// The determinant is constrained to satisfy `|det(a)| < 2^max_bits_det`, and so is every partial product
+/// `u[0][0] * ... * u[i][i]`; so the proof fails if any partial product is too large even if the final determinant is not.
+/// The rescaling also requires each partial product times the next diagonal entry to be less than `2^PRECISION_BITS` in
+/// absolute value (i.e., less than `2^(3*PRECISION_BITS)` before rescaling), which is constrained as well; this always holds if
+/// `|u[i][i]| < 2^max_bits_u` for all `i` and `max_bits_det + max_bits_u < PRECISION_BITS`
+///
+/// NOTE: `qmul` rounds after each multiplication and the entries of `u` carry the error of the decomposition, so the
+/// output is only an approximation of the determinant of `a`; its relative error grows with `N`
+pub fn lu_determinant<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    perm: &Vec<AssignedValue<F>>,
+    max_bits_det: u32,
+) -> AssignedValue<F> {
+    assert_eq!(u.num_rows, u.num_col);
+    assert_eq!(u.num_rows, perm.len());
+
+    let diag: Vec<AssignedValue<F>> = (0..u.num_rows).map(|i| u.matrix[i][i]).collect();
+    let prod = bounded_product(ctx, fpchip, &diag, max_bits_det);
+
+    let sign = permutation_sign(ctx, fpchip.range_gate(), perm);
+    // sign is +1 or -1 as a field element, so no rescaling is needed
+    let det = fpchip.gate().mul(ctx, prod, sign);
+    return det;
+}
+
+/// Outputs the determinant of `l*l^T` in fixed point representation for a Cholesky factor `l`,
+/// i.e., `det(l*l^T) = l[0][0]^2 * ... * l[N-1][N-1]^2`
+///
+/// `l` must be the lower triangular factor verified by `check_cholesky_factor_phase0` and `check_cholesky_phase1`, which
+/// check `a = l*l^T` with no shift, so that the output approximates `det(a)`; the positive diagonal of `l` also makes the
+/// output positive. A factor verified by `check_cholesky_phase0` instead gives `det(a - delta*Id)`
+///
+/// The determinant and every partial product is constrained to satisfy `|x| < 2^max_bits_det`; see `lu_determinant`
+pub fn cholesky_determinant<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    l: &ZkMatrix<F, PRECISION_BITS>,
+    max_bits_det: u32,
+) -> AssignedValue<F> {
+    assert_eq!(l.num_rows, l.num_col);
+
+    let mut factors: Vec<AssignedValue<F>> = Vec::new();
+    for i in 0..l.num_rows {
+        factors.push(l.matrix[i][i]);
+        factors.push(l.matrix[i][i]);
+    }
+    let det = bounded_product(ctx, fpchip, &factors, max_bits_det);
+    return det;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cholesky::{check_cholesky_factor_phase0, check_cholesky_phase1};
+    use crate::lu::load_permutation;
+    use crate::matrix::test_utils::*;
+
+    /// Checks that `permutation_sign` of `perm` is `sign`
+    fn sign_accepts(perm: Vec<usize>, sign: i64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let perm = load_permutation(ctx, &perm);
+            let out = permutation_sign(ctx, fpchip.range_gate(), &perm);
+            let expected = if sign < 0 { -Fr::from(1) } else { Fr::from(1) };
+            fpchip.gate().assert_is_const(ctx, &out, &expected);
+        });
+    }
+
+    /// Checks that `lu_determinant` of `u` and `perm` with the bound `max_bits_det` is `det`
+    fn lu_det_accepts(u: Vec<Vec<f64>>, perm: Vec<usize>, det: f64, max_bits_det: u32) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let u = ZkMatrix::new(ctx, fpchip, &u);
+            let perm = load_permutation(ctx, &perm);
+            let out = lu_determinant(ctx, fpchip, &u, &perm, max_bits_det);
+            fpchip.gate().assert_is_const(ctx, &out, &fpchip.quantization(det));
+        });
+    }
+
+    /// Checks the factor `l` of the symmetric positive definite `a` and that `cholesky_determinant` of `l` is `det`
+    fn cholesky_det_accepts(a: Vec<Vec<f64>>, l: Vec<Vec<f64>>, det: f64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &a);
+            let l = ZkMatrix::new(ctx, fpchip, &l);
+            let (l_t, l_times_lt) = check_cholesky_factor_phase0(ctx, fpchip, &a, &l, 1e-8, 4);
+            let init_rand = mock_init_rand(ctx);
+            check_cholesky_phase1(ctx, fpchip, &l, &l_t, &l_times_lt, &init_rand, 2);
+            let out = cholesky_determinant(ctx, fpchip, &l, 8);
+            fpchip.gate().assert_is_const(ctx, &out, &fpchip.quantization(det));
+        });
+    }
+
+    #[test]
+    fn test_permutation_sign() {
+        assert!(sign_accepts(vec![0, 1, 2], 1));
+        assert!(sign_accepts(vec![1, 0, 2], -1));
+        assert!(sign_accepts(vec![2, 0, 1], 1));
+    }
+
+    #[test]
+    fn test_permutation_sign_wrong_claim() {
+        assert!(!sign_accepts(vec![1, 0, 2], 1));
+        assert!(!sign_accepts(vec![2, 0, 1], -1));
+    }
+
+    #[test]
+    fn test_lu_determinant_sign() {
+        let u = vec![vec![4.0, 3.0], vec![0.0, 1.25]];
+        assert!(lu_det_accepts(u.clone(), vec![1, 0], -5.0, 4));
+        assert!(!lu_det_accepts(u, vec![1, 0], 5.0, 4));
+    }
+
+    #[test]
+    fn test_lu_determinant_unscaled_bound() {
+        // all partial products are below 2^max_bits_det in both cases, but for u[0][0] = 2^16 the second one is
+        // 2^(3*PRECISION_BITS) before rescaling
+        let diag_u = |u_00: f64| {
+            vec![
+                vec![u_00, 0.0, 0.0],
+                vec![0.0, 2.0_f64.powi(16), 0.0],
+                vec![0.0, 0.0, 2.0_f64.powi(-20)],
+            ]
+        };
+        let u_00 = 2.0_f64.powi(15);
+        assert!(lu_det_accepts(diag_u(u_00), vec![0, 1, 2], 2.0_f64.powi(11), 33));
+        let u_00 = 2.0_f64.powi(16);
+        assert!(!lu_det_accepts(diag_u(u_00), vec![0, 1, 2], 2.0_f64.powi(12), 33));
+    }
+
+    #[test]
+    fn test_cholesky_determinant() {
+        // [[4, 2], [2, 5]] = l*l^T for l = [[2, 0], [1, 2]]
+        let a = vec![vec![4.0, 2.0], vec![2.0, 5.0]];
+        let l = vec![vec![2.0, 0.0], vec![1.0, 2.0]];
+        assert!(cholesky_det_accepts(a.clone(), l.clone(), 16.0));
+        // det(a - Id) = 12 is not accepted for the unshifted factor
+        assert!(!cholesky_det_accepts(a, l, 12.0));
+    }
+}
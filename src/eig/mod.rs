@@ -0,0 +1,218 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, RangeChip};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+use halo2_base::Context;
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+
+/// Given a symmetric matrix `a` (`N X N` dimension), a matrix `q` (`N X N` dimension) and a vector `lambda` (`N` dimension)
+/// in fixed point representation with `fpchip`, performs the first part of checks that `a = q*Diag(lambda)*q^T` is the
+/// eigendecomposition of `a`, where the vector `lambda` is viewed as a diagonal matrix
+///
+/// `q` is orthogonal (its columns are the eigenvectors) and `lambda` is a vector of (signed) eigenvalues in decreasing order;
+/// `a` is constrained to be symmetric
+///
+/// Also takes as input tolerance levels `err_eig` and `err_q`, which determine the error up to which `a*q = q*Diag(lambda)`
+/// and `q*q^T = Id` are checked; these are computed exactly as `err_svd` and `err_u` using `err_calc`
+///
+/// `max_bits_lambda` can be set to be anything <= PRECISION_BITS; the eigenvalues are constrained to satisfy `|lambda[i]| < 2^max_bits_lambda`,
+/// which is used to make sure no overflows occur while multiplying
+///
+/// Must call `check_eig_sym_phase1` function following this function in the second phase to complete the check
+///
+/// The outputs are simply witnesses to be used for the corresponding variables in `check_eig_sym_phase1`
+///
+/// NOTE: as for `check_svd_phase0`, the fixed point chip does not check for overflows, so one needs to place some bound on
+/// $\Vert a \Vert_2$, which is assumed to be enforced by the function or program calling this library
+pub fn check_eig_sym_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    q: &ZkMatrix<F, PRECISION_BITS>,
+    lambda: &ZkVector<F, PRECISION_BITS>,
+    err_eig: f64,
+    err_q: f64,
+    max_bits_lambda: u32,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>, Vec<Vec<AssignedValue<F>>>) {
+    assert_eq!(a.num_rows, a.num_col);
+    assert_eq!(q.num_rows, q.num_col);
+    assert_eq!(a.num_rows, q.num_rows);
+    assert_eq!(a.num_rows, lambda.size());
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    check_mat_symmetric(ctx, &a.matrix);
+
+    // check the entries of lambda are in (-2^(max_bits_lambda + precision_bits), 2^(max_bits_lambda + precision_bits))
+    let max_bits = (max_bits_lambda + PRECISION_BITS) as usize;
+    lambda.entries_abs_less_than(ctx, &fpchip, max_bits);
+    // make sure lambda is in decreasing order; differences of the entries are in (-2^(max_bits+1), 2^(max_bits+1))
+    lambda.entries_in_desc_order(ctx, &fpchip, max_bits + 1);
+
+    // check that the entries of q correspond to real numbers in the interval (-1.0,1.0) upto an error of 2^-PRECISION_BITS
+    let unit_bnd_q = BigUint::from(2u64.pow(PRECISION_BITS) + 1);
+    check_mat_entries_bounded(ctx, &range, &q.matrix, &unit_bnd_q);
+
+    let q_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&q);
+
+    // define the doubly scaled errors
+    let err_eig_scale =
+        BigUint::from((err_eig * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+    let err_q_scale =
+        BigUint::from((err_q * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+
+    let q_times_lambda = mat_times_diag_mat(ctx, gate, &q.matrix, &lambda.v);
+    let a_times_q = honest_prover_mat_mul(ctx, &a.matrix, &q.matrix);
+    check_mat_diff(ctx, &range, &q_times_lambda, &a_times_q, &err_eig_scale);
+
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+    let quant_square = ctx.load_constant(quant * quant);
+
+    let q_times_qt = honest_prover_mat_mul(ctx, &q.matrix, &q_t.matrix);
+    check_mat_id(ctx, &range, &q_times_qt, &quant_square, &err_q_scale);
+
+    return (q_t, a_times_q, q_times_qt);
+}
+
+/// Second phase function for checking the eigendecomposition of a symmetric matrix;
+///
+/// `check_eig_sym_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `a`, `q` as used in `check_eig_sym_phase0` and other inputs correspond to the outputs of `check_eig_sym_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// Both products are checked together with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree `3N-2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_eig_sym_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    q: &ZkMatrix<F, PRECISION_BITS>,
+    q_t: &ZkMatrix<F, PRECISION_BITS>,
+    a_times_q: &Vec<Vec<AssignedValue<F>>>,
+    q_times_qt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(a, q, a_times_q), (q, q_t, q_times_qt)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+    use crate::svd::err_calc;
+
+    /// Which of the products committed to by `check_eig_sym_phase0` a malicious prover forges before the second phase
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Forge {
+        Honest,
+        ATimesQ,
+        QTimesQt,
+    }
+
+    /// Checks `q*Diag(lambda)*q^T` as the eigendecomposition of `a`, whose operator norm is at most 3, where the product
+    /// selected by `forge` has its last entry shifted by one in the field before the second phase
+    fn eig_forged_accepts(
+        a: Vec<Vec<f64>>,
+        q: Vec<Vec<f64>>,
+        lambda: Vec<f64>,
+        forge: Forge,
+    ) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &a);
+            let q = ZkMatrix::new(ctx, fpchip, &q);
+            let lambda = ZkVector::new(ctx, fpchip, &lambda);
+            let (err_eig, err_q) = err_calc(PRECISION_BITS, 2, 3.0, 0.0, 0.0);
+            let (q_t, a_times_q, q_times_qt) =
+                check_eig_sym_phase0(ctx, fpchip, &a, &q, &lambda, err_eig, err_q, 2);
+            let a_times_q =
+                if forge == Forge::ATimesQ { forge_last_entry(ctx, &a_times_q) } else { a_times_q };
+            let q_times_qt = if forge == Forge::QTimesQt {
+                forge_last_entry(ctx, &q_times_qt)
+            } else {
+                q_times_qt
+            };
+            let init_rand = mock_init_rand(ctx);
+            check_eig_sym_phase1(ctx, fpchip, &a, &q, &q_t, &a_times_q, &q_times_qt, &init_rand, 2);
+        });
+    }
+
+    /// Checks `q*Diag(lambda)*q^T` as the eigendecomposition of `a`
+    fn eig_accepts(a: Vec<Vec<f64>>, q: Vec<Vec<f64>>, lambda: Vec<f64>) -> bool {
+        return eig_forged_accepts(a, q, lambda, Forge::Honest);
+    }
+
+    /// The positive definite matrix `[[2, 1], [1, 2]]`, with eigenvalues 3 and 1
+    fn pos_def_a() -> Vec<Vec<f64>> {
+        return vec![vec![2.0, 1.0], vec![1.0, 2.0]];
+    }
+
+    /// The indefinite matrix `[[1, 2], [2, 1]]`, with eigenvalues 3 and -1
+    fn indefinite_a() -> Vec<Vec<f64>> {
+        return vec![vec![1.0, 2.0], vec![2.0, 1.0]];
+    }
+
+    #[test]
+    fn test_eig_sym_honest() {
+        let s = 0.5_f64.sqrt();
+        assert!(eig_accepts(pos_def_a(), vec![vec![s, -s], vec![s, s]], vec![3.0, 1.0]));
+    }
+
+    #[test]
+    fn test_eig_sym_indefinite() {
+        // the negative eigenvalue goes through the signed range check and the signed order check
+        let s = 0.5_f64.sqrt();
+        assert!(eig_accepts(indefinite_a(), vec![vec![s, s], vec![s, -s]], vec![3.0, -1.0]));
+    }
+
+    #[test]
+    fn test_eig_sym_misordered_lambda() {
+        // a valid eigendecomposition, but with the eigenvalues in increasing order
+        let s = 0.5_f64.sqrt();
+        assert!(!eig_accepts(pos_def_a(), vec![vec![s, s], vec![-s, s]], vec![1.0, 3.0]));
+    }
+
+    #[test]
+    fn test_eig_sym_misordered_signed_lambda() {
+        let s = 0.5_f64.sqrt();
+        assert!(!eig_accepts(indefinite_a(), vec![vec![s, s], vec![-s, s]], vec![-1.0, 3.0]));
+    }
+
+    #[test]
+    fn test_eig_sym_wrong_eigenvalue() {
+        let s = 0.5_f64.sqrt();
+        assert!(!eig_accepts(pos_def_a(), vec![vec![s, -s], vec![s, s]], vec![3.0, 0.9]));
+    }
+
+    #[test]
+    fn test_eig_sym_forged_products() {
+        let s = 0.5_f64.sqrt();
+        for forge in [Forge::ATimesQ, Forge::QTimesQt] {
+            let accepted = eig_forged_accepts(
+                indefinite_a(),
+                vec![vec![s, s], vec![s, -s]],
+                vec![3.0, -1.0],
+                forge,
+            );
+            assert!(!accepted, "forged {:?} was accepted", forge);
+        }
+    }
+
+    #[test]
+    fn test_eig_sym_not_symmetric() {
+        // a[0][1] is one quantization step above a[1][0], which is far below err_eig, so only the symmetry check fails
+        let mut a = pos_def_a();
+        a[0][1] += 2.0_f64.powi(-(PRECISION_BITS as i32));
+        let s = 0.5_f64.sqrt();
+        assert!(!eig_accepts(a, vec![vec![s, -s], vec![s, s]], vec![3.0, 1.0]));
+    }
+}
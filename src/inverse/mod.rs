@@ -0,0 +1,140 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+use halo2_base::Context;
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+
+/// Given a square matrix `a` (`N X N` dimension) and a claimed inverse `b` (`N X N` dimension) in fixed point representation
+/// with `fpchip`, performs the first part of the checks that `a*b = Id` and `b*a = Id`
+///
+/// Takes as input the tolerance `err_inv`, up to which the entries of `a*b` and `b*a` are checked to be those of the
+/// identity matrix (see `inverse_err_calc`)
+///
+/// `max_bits_b` bounds the entries of `b`: they are constrained to satisfy `|b[i][j]| < 2^max_bits_b`;
+/// it is used to make sure no overflows occur while multiplying and should be at least `log2(cond)` (see `inverse_err_calc`)
+///
+/// Must call `check_inverse_phase1` function following this function in the second phase to complete the check
+///
+/// The outputs are the unscaled products `a*b` and `b*a`, to be used for the corresponding variables in `check_inverse_phase1`
+///
+/// NOTE: as for `check_svd_phase0`, the fixed point chip does not check for overflows, so one needs to place some bound on
+/// $\Vert a \Vert_2$, which is assumed to be enforced by the function or program calling this library
+pub fn check_inverse_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    b: &ZkMatrix<F, PRECISION_BITS>,
+    err_inv: f64,
+    max_bits_b: u32,
+) -> (Vec<Vec<AssignedValue<F>>>, Vec<Vec<AssignedValue<F>>>) {
+    assert_eq!(a.num_rows, a.num_col);
+    assert_eq!(b.num_rows, b.num_col);
+    assert_eq!(a.num_rows, b.num_rows);
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+
+    // check that the entries of b correspond to real numbers in (-2^max_bits_b, 2^max_bits_b)
+    let b_bnd_q = BigUint::from(2u32).pow(max_bits_b + PRECISION_BITS);
+    check_mat_entries_bounded(ctx, &range, &b.matrix, &b_bnd_q);
+
+    // define the doubly scaled error
+    let err_inv_scale =
+        BigUint::from((err_inv * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+    let quant_square = ctx.load_constant(quant * quant);
+
+    let a_times_b = honest_prover_mat_mul(ctx, &a.matrix, &b.matrix);
+    check_mat_id(ctx, &range, &a_times_b, &quant_square, &err_inv_scale);
+
+    let b_times_a = honest_prover_mat_mul(ctx, &b.matrix, &a.matrix);
+    check_mat_id(ctx, &range, &b_times_a, &quant_square, &err_inv_scale);
+
+    return (a_times_b, b_times_a);
+}
+
+/// Second phase function for checking the inverse;
+///
+/// `check_inverse_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `a`, `b` as used in `check_inverse_phase0` and other inputs correspond to the outputs of `check_inverse_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// Both products are checked together with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree `3N-2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_inverse_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    b: &ZkMatrix<F, PRECISION_BITS>,
+    a_times_b: &Vec<Vec<AssignedValue<F>>>,
+    b_times_a: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(a, b, a_times_b), (b, a, b_times_a)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Calculates the tolerance `err_inv` for `check_inverse_phase0`
+///
+/// `p` is the PRECISION_BITS for the fixed point chip
+///
+/// `size` is the size of the matrices for which the output is to be used
+///
+/// `max_norm` is the maximum operator norm of `a` for which the output is to be used
+///
+/// `cond` is an upper bound on the condition number of `a`; the matrix `a` is assumed to be scaled so that
+/// $\Vert a \Vert_2 \geq 1$, so that $\Vert a^{-1} \Vert_2 \leq$ `cond`
+///
+/// `eps_inv` is the relative error parameter of the (floating point) algorithm used to compute the inverse;
+/// for a backward stable algorithm, the residual $\Vert a b - I \Vert$ is bounded by `eps_inv*cond`
+///
+/// Quantizing `a` and `b` changes each entry of `a*b` (and `b*a`) by at most
+/// `size*(max_norm + cond)*2^-(p+1)` up to second order terms
+pub fn inverse_err_calc(p: u32, size: usize, max_norm: f64, cond: f64, eps_inv: f64) -> f64 {
+    let precision = 2.0_f64.powf(-1.0 * (p as f64 + 1.0));
+    let err_inv = precision * (size as f64) * (max_norm + cond + precision) + cond * eps_inv;
+    return err_inv;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+
+    /// Checks `b` as the inverse of `[[2, 1], [1, 1]]`; if `forge` is set, the committed `a*b` is forged in the second phase
+    fn inverse_accepts(b: Vec<Vec<f64>>, forge: bool) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &vec![vec![2.0, 1.0], vec![1.0, 1.0]]);
+            let b = ZkMatrix::new(ctx, fpchip, &b);
+            let err_inv = inverse_err_calc(PRECISION_BITS, 2, 3.0, 10.0, 0.0);
+            let (a_times_b, b_times_a) = check_inverse_phase0(ctx, fpchip, &a, &b, err_inv, 4);
+            let a_times_b = if forge { forge_last_entry(ctx, &a_times_b) } else { a_times_b };
+            let init_rand = mock_init_rand(ctx);
+            check_inverse_phase1(ctx, fpchip, &a, &b, &a_times_b, &b_times_a, &init_rand, 2);
+        });
+    }
+
+    #[test]
+    fn test_inverse_honest() {
+        assert!(inverse_accepts(vec![vec![1.0, -1.0], vec![-1.0, 2.0]], false));
+    }
+
+    #[test]
+    fn test_inverse_wrong_entry() {
+        assert!(!inverse_accepts(vec![vec![1.01, -1.0], vec![-1.0, 2.0]], false));
+    }
+
+    #[test]
+    fn test_inverse_forged_product() {
+        assert!(!inverse_accepts(vec![vec![1.0, -1.0], vec![-1.0, 2.0]], true));
+    }
+}
@@ -1,7 +1,20 @@
 #![feature(return_position_impl_trait_in_trait)]
 #![allow(incomplete_features)]
 
+pub mod cholesky;
 pub mod circuits;
+pub mod det;
+pub mod eig;
+pub mod inverse;
+pub mod lu;
+pub mod markov;
 pub mod matrix;
+pub mod nn;
+pub mod pinv;
+pub mod polynomial;
+pub mod qr;
+pub mod regression;
 pub mod scaffold;
+pub mod solve;
+pub mod stats;
 pub mod svd;
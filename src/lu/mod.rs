@@ -0,0 +1,291 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+
+/// Loads the permutation `perm` of `{0, 1, ..., N-1}` (given as an index vector) into the context `ctx`
+///
+/// Does not constrain the output in anyway; use `permutation_matrix` to constrain it to be a permutation
+pub fn load_permutation<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    perm: &Vec<usize>,
+) -> Vec<AssignedValue<F>> {
+    let mut assigned_perm: Vec<AssignedValue<F>> = Vec::new();
+    for i in perm {
+        assigned_perm.push(ctx.load_witness(F::from(*i as u64)));
+    }
+    return assigned_perm;
+}
+
+/// Expands the index vector `perm` (of length `N`) into the `N X N` permutation matrix `p` with `p[i][j] = 1` iff `perm[i] = j`,
+/// so that row `i` of `p*a` is row `perm[i]` of `a`
+///
+/// Constrains `p` to be a boolean matrix with exactly one 1 in each row and each column, i.e., constrains `perm` to be a permutation
+///
+/// The entries of `p` are the field elements 0 and 1 (and *not* fixed point encoded)
+///
+/// Leads to O(N^2) cells: one `is_equal` and two additions per entry
+pub fn permutation_matrix<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    perm: &Vec<AssignedValue<F>>,
+) -> ZkMatrix<F, PRECISION_BITS> {
+    #![allow(non_snake_case)]
+    let N = perm.len();
+    let mut p: Vec<Vec<AssignedValue<F>>> = Vec::new();
+    for i in 0..N {
+        let mut row: Vec<AssignedValue<F>> = Vec::new();
+        for j in 0..N {
+            // is_equal outputs a boolean
+            row.push(gate.is_equal(ctx, perm[i], Constant(F::from(j as u64))));
+        }
+        // exactly one 1 per row; this also makes sure perm[i] is in {0, 1, ..., N-1}
+        let row_sum = gate.sum(ctx, row.clone());
+        gate.assert_is_const(ctx, &row_sum, &F::one());
+        p.push(row);
+    }
+    for j in 0..N {
+        // exactly one 1 per column
+        let col_sum = gate.sum(ctx, p.iter().map(|row| row[j]).collect::<Vec<_>>());
+        gate.assert_is_const(ctx, &col_sum, &F::one());
+    }
+    return ZkMatrix { matrix: p, num_rows: N, num_col: N };
+}
+
+/// Given a square matrix `a` (`N X N` dimension), a permutation `perm` of `{0, 1, ..., N-1}`, and matrices `l` and `u`
+/// (`N X N` dimension) in fixed point representation with `fpchip`, performs the first part of checks that `p*a = l*u` is an
+/// LU decomposition of `a` with partial pivoting, where `p` is the permutation matrix of `perm` (see `permutation_matrix`)
+///
+/// `l` is constrained to be unit lower triangular with entries in `[-1, 1]` (as guaranteed by partial pivoting) and
+/// `u` is constrained to be upper triangular with entries satisfying `|u[i][j]| < 2^max_bits_u`;
+/// `max_bits_u` is used to make sure no overflows occur while multiplying
+///
+/// Also takes as input the tolerance `err_lu`, up to which `p*a = l*u` is checked (see `lu_err_calc`)
+///
+/// Must call `check_lu_phase1` function following this function in the second phase to complete the check
+///
+/// Outputs the loaded permutation `perm`, the permutation matrix `p`, and the unscaled products `p*a` and `l*u`;
+/// `perm` can be used to compute the sign of the permutation, and the others are witnesses to be used for the
+/// corresponding variables in `check_lu_phase1`
+///
+/// NOTE: the fixed point chip does not check for overflows, so one needs to place some bound on $\Vert a \Vert_2$,
+/// which is assumed to be enforced by the function or program calling this library
+pub fn check_lu_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    perm: &Vec<usize>,
+    l: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    err_lu: f64,
+    max_bits_u: u32,
+) -> (
+    Vec<AssignedValue<F>>,
+    ZkMatrix<F, PRECISION_BITS>,
+    Vec<Vec<AssignedValue<F>>>,
+    Vec<Vec<AssignedValue<F>>>,
+) {
+    assert_eq!(a.num_rows, a.num_col);
+    assert_eq!(perm.len(), a.num_rows);
+    assert_eq!((l.num_rows, l.num_col), (a.num_rows, a.num_col));
+    assert_eq!((u.num_rows, u.num_col), (a.num_rows, a.num_col));
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    let perm = load_permutation(ctx, perm);
+    let p: ZkMatrix<F, PRECISION_BITS> = permutation_matrix(ctx, gate, &perm);
+
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    // l is unit lower triangular with entries in [-1, 1]
+    check_lower_triangular(ctx, gate, &l.matrix);
+    for i in 0..l.num_rows {
+        gate.assert_is_const(ctx, &l.matrix[i][i], &quant);
+    }
+    // unit_bnd_q = quantization of 1+2^-PRECISION_BITS
+    let unit_bnd_q = BigUint::from(2u64.pow(PRECISION_BITS) + 1);
+    check_mat_entries_bounded(ctx, &range, &l.matrix, &unit_bnd_q);
+
+    // u is upper triangular with entries in (-2^max_bits_u, 2^max_bits_u)
+    check_upper_triangular(ctx, gate, &u.matrix);
+    let u_bnd_q = BigUint::from(2u32).pow(max_bits_u + PRECISION_BITS);
+    check_mat_entries_bounded(ctx, &range, &u.matrix, &u_bnd_q);
+
+    // define the doubly scaled error
+    let err_lu_scale =
+        BigUint::from((err_lu * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+
+    // p is not fixed point encoded, so p*a is only scaled once and needs to be scaled once more before comparing
+    let p_times_a = honest_prover_mat_mul(ctx, &p.matrix, &a.matrix);
+    let l_times_u = honest_prover_mat_mul(ctx, &l.matrix, &u.matrix);
+    let pa_scaled = field_mat_scalar_mul(ctx, gate, &p_times_a, quant);
+    check_mat_diff(ctx, &range, &l_times_u, &pa_scaled, &err_lu_scale);
+
+    return (perm, p, p_times_a, l_times_u);
+}
+
+/// Second phase function for checking the LU decomposition;
+///
+/// `check_lu_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `a`, `l`, `u` as used in `check_lu_phase0` and other inputs correspond to the outputs of `check_lu_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// Both products are checked together with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree `3N-2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_lu_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    l: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    p: &ZkMatrix<F, PRECISION_BITS>,
+    p_times_a: &Vec<Vec<AssignedValue<F>>>,
+    l_times_u: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(p, a, p_times_a), (l, u, l_times_u)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Calculates `err_lu` for `check_lu_phase0` from `eps_lu`
+///
+/// `p` is the PRECISION_BITS for the fixed point chip
+///
+/// `size` is the size of the matrices for which the output is to be used
+///
+/// `max_u` is the maximum absolute value of the entries of `u` (e.g. `2^max_bits_u`)
+///
+/// `eps_lu` is the entrywise error of `p*a - l*u` for the (floating point) decomposition before quantization
+///
+/// Quantizing `l` and `u` changes each entry of `l*u` by at most `size*(1 + max_u)*2^-(p+1)` (as the entries of `l` are
+/// at most 1) and quantizing `a` changes its entries by at most `2^-(p+1)`, up to second order terms
+pub fn lu_err_calc(p: u32, size: usize, max_u: f64, eps_lu: f64) -> f64 {
+    let precision = 2.0_f64.powf(-1.0 * (p as f64 + 1.0));
+    let err_lu = precision * (size as f64) * (1.0 + max_u + precision) + precision + eps_lu;
+    return err_lu;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+
+    /// Checks `perm` with `permutation_matrix`
+    fn permutation_accepts(perm: Vec<usize>) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let perm = load_permutation(ctx, &perm);
+            let _p: ZkMatrix<_, PRECISION_BITS> = permutation_matrix(ctx, fpchip.gate(), &perm);
+        });
+    }
+
+    /// Which of the products committed to by `check_lu_phase0` a malicious prover forges before the second phase
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Forge {
+        Honest,
+        PTimesA,
+        LTimesU,
+    }
+
+    /// Checks `p*a = l*u` for `a = [[1, 2], [4, 3]]`, where the product selected by `forge` has its last entry shifted by one
+    /// in the field before the second phase
+    fn lu_forged_accepts(
+        perm: Vec<usize>,
+        l: Vec<Vec<f64>>,
+        u: Vec<Vec<f64>>,
+        forge: Forge,
+    ) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &vec![vec![1.0, 2.0], vec![4.0, 3.0]]);
+            let l = ZkMatrix::new(ctx, fpchip, &l);
+            let u = ZkMatrix::new(ctx, fpchip, &u);
+            let err_lu = lu_err_calc(PRECISION_BITS, 2, 8.0, 0.0);
+            let (_perm, p, p_times_a, l_times_u) =
+                check_lu_phase0(ctx, fpchip, &a, &perm, &l, &u, err_lu, 3);
+            let p_times_a =
+                if forge == Forge::PTimesA { forge_last_entry(ctx, &p_times_a) } else { p_times_a };
+            let l_times_u =
+                if forge == Forge::LTimesU { forge_last_entry(ctx, &l_times_u) } else { l_times_u };
+            let init_rand = mock_init_rand(ctx);
+            check_lu_phase1(ctx, fpchip, &a, &l, &u, &p, &p_times_a, &l_times_u, &init_rand, 2);
+        });
+    }
+
+    /// Checks `p*a = l*u` for `a = [[1, 2], [4, 3]]`
+    fn lu_accepts(perm: Vec<usize>, l: Vec<Vec<f64>>, u: Vec<Vec<f64>>) -> bool {
+        return lu_forged_accepts(perm, l, u, Forge::Honest);
+    }
+
+    #[test]
+    fn test_permutation_matrix() {
+        assert!(permutation_accepts(vec![2, 0, 1]));
+    }
+
+    #[test]
+    fn test_permutation_matrix_repeated_index() {
+        assert!(!permutation_accepts(vec![0, 0, 1]));
+    }
+
+    #[test]
+    fn test_permutation_matrix_index_out_of_range() {
+        assert!(!permutation_accepts(vec![0, 1, 3]));
+    }
+
+    #[test]
+    fn test_lu_honest() {
+        let l = vec![vec![1.0, 0.0], vec![0.25, 1.0]];
+        let u = vec![vec![4.0, 3.0], vec![0.0, 1.25]];
+        assert!(lu_accepts(vec![1, 0], l, u));
+    }
+
+    #[test]
+    fn test_lu_wrong_permutation() {
+        // l*u is the decomposition of a with its rows swapped, but perm claims the identity
+        let l = vec![vec![1.0, 0.0], vec![0.25, 1.0]];
+        let u = vec![vec![4.0, 3.0], vec![0.0, 1.25]];
+        assert!(!lu_accepts(vec![0, 1], l, u));
+    }
+
+    #[test]
+    fn test_lu_non_unit_diagonal() {
+        // l*u = p*a, but l[1][1] = 0.5
+        let l = vec![vec![1.0, 0.0], vec![0.25, 0.5]];
+        let u = vec![vec![4.0, 3.0], vec![0.0, 2.5]];
+        assert!(!lu_accepts(vec![1, 0], l, u));
+    }
+
+    #[test]
+    fn test_lu_l_not_lower_triangular() {
+        // l*u = p*a, but l[0][1] = -1
+        let l = vec![vec![1.0, -1.0], vec![0.25, 1.0]];
+        let u = vec![vec![4.0, 4.0], vec![0.0, 1.0]];
+        assert!(!lu_accepts(vec![1, 0], l, u));
+    }
+
+    #[test]
+    fn test_lu_u_not_upper_triangular() {
+        // l = Id and u = p*a, which has a nonzero entry below the diagonal
+        let l = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+        let u = vec![vec![4.0, 3.0], vec![1.0, 2.0]];
+        assert!(!lu_accepts(vec![1, 0], l, u));
+    }
+
+    #[test]
+    fn test_lu_forged_products() {
+        let l = vec![vec![1.0, 0.0], vec![0.25, 1.0]];
+        let u = vec![vec![4.0, 3.0], vec![0.0, 1.25]];
+        for forge in [Forge::PTimesA, Forge::LTimesU] {
+            let accepted = lu_forged_accepts(vec![1, 0], l.clone(), u.clone(), forge);
+            assert!(!accepted, "forged {:?} was accepted", forge);
+        }
+    }
+}
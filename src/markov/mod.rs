@@ -0,0 +1,269 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use num_bigint::BigUint;
+use num_traits::FromPrimitive;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+
+/// Converts the tolerance `err` into the bound used with `check_abs_less_than` at the scale `2^scale_bits`;
+/// the resulting check is `|x| <= round(err*2^scale_bits)`, so `err = 0.0` requires exact equality
+///
+/// The scaling is done in `f64`, since `2^scale_bits` does not fit in a `u128` for `scale_bits = 3*PRECISION_BITS` with
+/// `PRECISION_BITS >= 43`
+fn tol_bound(err: f64, scale_bits: u32) -> BigUint {
+    assert!(err >= 0.0);
+    let err_scale = BigUint::from_f64((err * 2f64.powi(scale_bits as i32)).round()).unwrap();
+    return err_scale + BigUint::from(1u32);
+}
+
+/// Given a fixed point vector `v` and a fixed point number `target`, constrains the entries of `v` to be non-negative and
+/// at most `2^max_bits` (as quantized values), and checks that `|sum(v) - target| <= err_sum`
+fn check_nonneg_sum<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    v: &Vec<AssignedValue<F>>,
+    target: F,
+    max_bits: usize,
+    err_sum: f64,
+) {
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    for elem in v {
+        range.range_check(ctx, *elem, max_bits);
+    }
+    let sum = gate.sum(ctx, v.clone());
+    let diff = gate.sub(ctx, sum, Constant(target));
+    check_abs_less_than(ctx, &range, diff, &tol_bound(err_sum, PRECISION_BITS));
+}
+
+/// Checks that the fixed point matrix `p` (`N X N` dimension) is column stochastic, i.e., that all of its entries are
+/// non-negative and each of its columns sums to one up to `err_sum`; use `err_sum = 0.0` to require the exact sum
+///
+/// The entries are range checked to `PRECISION_BITS + 1` bits, which together with the column sums bounds them by `1 + err_sum`
+///
+/// Leads to about `N^2` range checks
+pub fn check_column_stochastic<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    p: &ZkMatrix<F, PRECISION_BITS>,
+    err_sum: f64,
+) {
+    assert_eq!(p.num_rows, p.num_col);
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    let p_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&p);
+    for col in &p_t.matrix {
+        check_nonneg_sum(ctx, fpchip, col, quant, (PRECISION_BITS + 1) as usize, err_sum);
+    }
+}
+
+/// Given a column stochastic matrix `p` (`N X N` dimension) and a probability vector `pi` (`N` dimension) in fixed point
+/// representation with `fpchip`, checks that `pi` is a stationary distribution of `p`, i.e., that `|p.pi - pi|_inf <= err_pi`
+///
+/// Also checks that `p` is column stochastic (see `check_column_stochastic`) and that `pi` is non-negative and sums to one
+/// up to `err_sum`
+///
+/// `p.pi` is computed with `field_mat_vec_mul` and compared with `pi` without any rescaling, at the scale `2^(2*PRECISION_BITS)`;
+/// so no second phase is needed
+///
+/// Leads to about `N^2` cells for the product, and about `N^2 + 2*N` range checks
+///
+/// NOTE: a stationary distribution is unique only if `p` is irreducible; the check does not constrain this
+pub fn check_stationary<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    p: &ZkMatrix<F, PRECISION_BITS>,
+    pi: &ZkVector<F, PRECISION_BITS>,
+    err_pi: f64,
+    err_sum: f64,
+) {
+    assert_eq!(p.num_col, pi.size());
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    check_column_stochastic(ctx, fpchip, p, err_sum);
+    check_nonneg_sum(ctx, fpchip, &pi.v, quant, (PRECISION_BITS + 1) as usize, err_sum);
+
+    // p.pi has scale 2^(2*PRECISION_BITS)
+    let p_pi = field_mat_vec_mul(ctx, gate, &p.matrix, &pi.v);
+    let pi_scaled = field_mat_scalar_mul(ctx, gate, &vec![pi.v.clone()], quant);
+    check_mat_diff(ctx, &range, &vec![p_pi], &pi_scaled, &tol_bound(err_pi, 2 * PRECISION_BITS));
+}
+
+/// Given a column stochastic matrix `p` (`N X N` dimension) and a probability vector `pi` (`N` dimension) in fixed point
+/// representation with `fpchip`, checks that `pi` is the PageRank vector of `p` with damping factor `alpha`, i.e., that
+/// `|alpha*p.pi + (1 - alpha)/N - pi|_inf <= err_pi`
+///
+/// `alpha` is a public constant in `[0, 1]` (usually `0.85`); `p` is typically the transition matrix of a graph whose column `j`
+/// is uniform over the out-links of node `j` (and over all nodes if `j` has none)
+///
+/// Also checks that `p` is column stochastic (see `check_column_stochastic`) and that `pi` is non-negative and sums to one
+/// up to `err_sum`
+///
+/// The two sides are compared without any rescaling, at the scale `2^(3*PRECISION_BITS)`; so no second phase is needed
+///
+/// The constants `alpha` and `(1 - alpha)/N` are quantized, which changes the right hand side by up to
+/// `2^-(PRECISION_BITS+1)*(1 + (1 + err_sum)^2)` in each entry (the entries of `p.pi` are at most `(1 + err_sum)^2`);
+/// this quantization error is added to `err_pi`, so the check passes for any `pi` within `err_pi` of the exact PageRank equation
+///
+/// Leads to about `2*N^2` cells, and about `N^2 + 2*N` range checks
+pub fn check_pagerank<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    p: &ZkMatrix<F, PRECISION_BITS>,
+    pi: &ZkVector<F, PRECISION_BITS>,
+    alpha: f64,
+    err_pi: f64,
+    err_sum: f64,
+) {
+    #![allow(non_snake_case)]
+    let N = pi.size();
+    assert_eq!(p.num_col, N);
+    assert!(alpha >= 0.0 && alpha <= 1.0);
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    check_column_stochastic(ctx, fpchip, p, err_sum);
+    check_nonneg_sum(ctx, fpchip, &pi.v, quant, (PRECISION_BITS + 1) as usize, err_sum);
+
+    // p.pi has scale 2^(2*PRECISION_BITS); alpha*p.pi and the teleportation term have scale 2^(3*PRECISION_BITS)
+    let p_pi = field_mat_vec_mul(ctx, gate, &p.matrix, &pi.v);
+    let alpha_q = fpchip.quantization(alpha);
+    let teleport = fpchip.quantization((1.0 - alpha) / (N as f64)) * quant * quant;
+    let mut rhs: Vec<AssignedValue<F>> = Vec::new();
+    for elem in p_pi {
+        rhs.push(gate.mul_add(ctx, elem, Constant(alpha_q), Constant(teleport)));
+    }
+    let pi_scaled = field_mat_scalar_mul(ctx, gate, &vec![pi.v.clone()], quant * quant);
+    let quant_err =
+        2.0_f64.powf(-1.0 * (PRECISION_BITS as f64 + 1.0)) * (1.0 + (1.0 + err_sum).powi(2));
+    let tol = tol_bound(err_pi + quant_err, 3 * PRECISION_BITS);
+    check_mat_diff(ctx, &range, &vec![rhs], &pi_scaled, &tol);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+
+    /// Column stochastic matrix whose stationary distribution is `(1/3, 2/3)`
+    fn two_state() -> Vec<Vec<f64>> {
+        return vec![vec![0.5, 0.25], vec![0.5, 0.75]];
+    }
+
+    /// Transition matrix of the graph `0 -> 1`, `1 -> 2`, `2 -> 0`, `2 -> 1`
+    fn three_node_graph() -> Vec<Vec<f64>> {
+        return vec![vec![0.0, 0.0, 0.5], vec![1.0, 0.0, 0.5], vec![0.0, 1.0, 0.0]];
+    }
+
+    /// Reference PageRank vector computed in f64 by power iteration
+    fn pagerank_f64(p: &Vec<Vec<f64>>, alpha: f64) -> Vec<f64> {
+        let n = p.len();
+        let mut pi = vec![1.0 / (n as f64); n];
+        for _ in 0..500 {
+            pi = (0..n)
+                .map(|i| {
+                    let p_pi: f64 = (0..n).map(|j| p[i][j] * pi[j]).sum();
+                    alpha * p_pi + (1.0 - alpha) / (n as f64)
+                })
+                .collect();
+        }
+        return pi;
+    }
+
+    /// Checks that `p` is column stochastic up to `err_sum`
+    fn column_stochastic_accepts(p: Vec<Vec<f64>>, err_sum: f64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let p = ZkMatrix::new(ctx, fpchip, &p);
+            check_column_stochastic(ctx, fpchip, &p, err_sum);
+        });
+    }
+
+    /// Checks that `pi` is a stationary distribution of `p` up to `1e-9`
+    fn stationary_accepts(p: Vec<Vec<f64>>, pi: Vec<f64>) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let p = ZkMatrix::new(ctx, fpchip, &p);
+            let pi = ZkVector::new(ctx, fpchip, &pi);
+            check_stationary(ctx, fpchip, &p, &pi, 1e-9, 1e-9);
+        });
+    }
+
+    /// Checks that `pi` is the PageRank vector of `p` with damping factor `alpha` up to `1e-9`
+    fn pagerank_accepts(p: Vec<Vec<f64>>, pi: Vec<f64>, alpha: f64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let p = ZkMatrix::new(ctx, fpchip, &p);
+            let pi = ZkVector::new(ctx, fpchip, &pi);
+            check_pagerank(ctx, fpchip, &p, &pi, alpha, 1e-9, 1e-9);
+        });
+    }
+
+    #[test]
+    fn test_tol_bound() {
+        assert_eq!(tol_bound(0.0, 96), BigUint::from(1u32));
+        assert_eq!(tol_bound(0.75, 2), BigUint::from(4u32));
+        // 3*PRECISION_BITS for PRECISION_BITS = 48 is beyond the range of u128
+        assert_eq!(tol_bound(0.5, 144), BigUint::from(2u32).pow(143) + BigUint::from(1u32));
+    }
+
+    #[test]
+    fn test_column_stochastic() {
+        assert!(column_stochastic_accepts(two_state(), 0.0));
+        assert!(column_stochastic_accepts(three_node_graph(), 0.0));
+    }
+
+    #[test]
+    fn test_column_stochastic_negative_entry() {
+        // the columns still sum to one
+        assert!(!column_stochastic_accepts(vec![vec![1.25, 0.5], vec![-0.25, 0.5]], 1e-9));
+    }
+
+    #[test]
+    fn test_column_stochastic_wrong_sum() {
+        let p = vec![vec![0.5, 0.25], vec![0.25, 0.75]];
+        assert!(!column_stochastic_accepts(p.clone(), 1e-9));
+        // the first column sums to 0.75, which is within a tolerance of 0.25
+        assert!(column_stochastic_accepts(p, 0.25));
+    }
+
+    #[test]
+    fn test_stationary() {
+        assert!(stationary_accepts(two_state(), vec![1.0 / 3.0, 2.0 / 3.0]));
+    }
+
+    #[test]
+    fn test_stationary_wrong_pi() {
+        assert!(!stationary_accepts(two_state(), vec![0.5, 0.5]));
+        assert!(!stationary_accepts(two_state(), vec![2.0 / 3.0, 1.0 / 3.0]));
+    }
+
+    #[test]
+    fn test_pagerank() {
+        let p = three_node_graph();
+        let pi = pagerank_f64(&p, 0.85);
+        assert!(pagerank_accepts(p, pi, 0.85));
+    }
+
+    #[test]
+    fn test_pagerank_forged() {
+        let p = three_node_graph();
+        let mut pi = pagerank_f64(&p, 0.85);
+        // still a probability vector
+        pi[0] += 0.01;
+        pi[2] -= 0.01;
+        assert!(!pagerank_accepts(p.clone(), pi, 0.85));
+        // the stationary distribution (alpha = 1) is not the PageRank vector for alpha = 0.85
+        let stationary = pagerank_f64(&p, 1.0);
+        assert!(pagerank_accepts(p.clone(), stationary.clone(), 1.0));
+        assert!(!pagerank_accepts(p, stationary, 0.85));
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+
+/// Errors reported by the `try_*` variants of the functions in the `matrix` and `svd` modules
+/// when their inputs do not have the required dimensions
+///
+/// Dimensions are reported as `(num_rows, num_col)`; `operand` is the name of the argument at fault
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MatrixError {
+    /// `operand` has no rows or no columns
+    Empty { operand: &'static str },
+    /// Row `row` of `operand` has `found` entries while row 0 has `expected` entries
+    RaggedRows { operand: &'static str, row: usize, expected: usize, found: usize },
+    /// The `num_rows` and `num_col` fields of the ZkMatrix `operand` do not match its entries
+    InconsistentDims { operand: &'static str, stored: (usize, usize), actual: (usize, usize) },
+    /// Operands `lhs` and `rhs` cannot be combined in the operation `op`
+    ShapeMismatch {
+        op: &'static str,
+        lhs: &'static str,
+        lhs_dims: (usize, usize),
+        rhs: &'static str,
+        rhs_dims: (usize, usize),
+    },
+    /// Matrix `matrix` and vector `vector` cannot be combined in the operation `op`
+    VectorLengthMismatch {
+        op: &'static str,
+        matrix: &'static str,
+        dims: (usize, usize),
+        vector: &'static str,
+        len: usize,
+    },
+    /// `operand` should be a square (unitary) matrix
+    NotSquare { operand: &'static str, dims: (usize, usize) },
+    /// The vector of singular values `operand` should have length `min{N, M}` for the `N X M` matrix `matrix`
+    SingularValueLength {
+        operand: &'static str,
+        matrix: &'static str,
+        dims: (usize, usize),
+        found: usize,
+    },
+    /// The Freivalds check in the operation `op` was asked for zero repetitions, which would check nothing
+    ZeroRepetitions { op: &'static str },
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixError::Empty { operand } => write!(f, "matrix `{}` is empty", operand),
+            MatrixError::RaggedRows { operand, row, expected, found } => write!(
+                f,
+                "matrix `{}` is ragged: row {} has {} entries but row 0 has {}",
+                operand, row, found, expected
+            ),
+            MatrixError::InconsistentDims { operand, stored, actual } => write!(
+                f,
+                "matrix `{}` claims to be {} X {} but its entries are {} X {}",
+                operand, stored.0, stored.1, actual.0, actual.1
+            ),
+            MatrixError::ShapeMismatch { op, lhs, lhs_dims, rhs, rhs_dims } => write!(
+                f,
+                "{}: `{}` ({} X {}) and `{}` ({} X {}) have incompatible shapes",
+                op, lhs, lhs_dims.0, lhs_dims.1, rhs, rhs_dims.0, rhs_dims.1
+            ),
+            MatrixError::VectorLengthMismatch { op, matrix, dims, vector, len } => write!(
+                f,
+                "{}: `{}` ({} X {}) and vector `{}` (length {}) have incompatible shapes",
+                op, matrix, dims.0, dims.1, vector, len
+            ),
+            MatrixError::NotSquare { operand, dims } => {
+                write!(f, "matrix `{}` should be square but is {} X {}", operand, dims.0, dims.1)
+            }
+            MatrixError::SingularValueLength { operand, matrix, dims, found } => write!(
+                f,
+                "vector `{}` should have length min{{N, M}} = {} for `{}` ({} X {}) but has length {}",
+                operand,
+                std::cmp::min(dims.0, dims.1),
+                matrix,
+                dims.0,
+                dims.1,
+                found
+            ),
+            MatrixError::ZeroRepetitions { op } => {
+                write!(f, "{}: the number of repetitions `num_reps` must be at least 1", op)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MatrixError {}
+
+/// Outputs the dimensions `(num_rows, num_col)` of `a`, or an error if `a` is empty or its rows have different sizes
+pub fn matrix_dims<T>(
+    operand: &'static str,
+    a: &Vec<Vec<T>>,
+) -> Result<(usize, usize), MatrixError> {
+    if a.is_empty() || a[0].is_empty() {
+        return Err(MatrixError::Empty { operand });
+    }
+    let num_col = a[0].len();
+    for (row, elems) in a.iter().enumerate() {
+        if elems.len() != num_col {
+            return Err(MatrixError::RaggedRows {
+                operand,
+                row,
+                expected: num_col,
+                found: elems.len(),
+            });
+        }
+    }
+    return Ok((a.len(), num_col));
+}
+
+/// Checks that `a*b` is well defined (and, if `c` is given, that it has the dimensions of `a*b`) for matrices of dimensions
+/// `a_dims`, `b_dims` (and `c_dims`)
+pub fn check_mul_dims(
+    op: &'static str,
+    a: (&'static str, (usize, usize)),
+    b: (&'static str, (usize, usize)),
+    c: Option<(&'static str, (usize, usize))>,
+) -> Result<(), MatrixError> {
+    let (a_name, a_dims) = a;
+    let (b_name, b_dims) = b;
+    if a_dims.1 != b_dims.0 {
+        return Err(MatrixError::ShapeMismatch {
+            op,
+            lhs: a_name,
+            lhs_dims: a_dims,
+            rhs: b_name,
+            rhs_dims: b_dims,
+        });
+    }
+    if let Some((c_name, c_dims)) = c {
+        if c_dims != (a_dims.0, b_dims.1) {
+            return Err(MatrixError::ShapeMismatch {
+                op,
+                lhs: c_name,
+                lhs_dims: c_dims,
+                rhs: "product",
+                rhs_dims: (a_dims.0, b_dims.1),
+            });
+        }
+    }
+    return Ok(());
+}
+
+/// Checks that the Freivalds check in the operation `op` is repeated at least once
+pub fn check_num_reps(op: &'static str, num_reps: usize) -> Result<(), MatrixError> {
+    if num_reps == 0 {
+        return Err(MatrixError::ZeroRepetitions { op });
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matrix_dims() {
+        let a = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        assert_eq!(matrix_dims("a", &a), Ok((2, 3)));
+    }
+
+    #[test]
+    fn test_matrix_dims_empty() {
+        let no_rows: Vec<Vec<u64>> = vec![];
+        assert_eq!(matrix_dims("a", &no_rows), Err(MatrixError::Empty { operand: "a" }));
+        let no_cols: Vec<Vec<u64>> = vec![vec![], vec![]];
+        assert_eq!(matrix_dims("a", &no_cols), Err(MatrixError::Empty { operand: "a" }));
+    }
+
+    #[test]
+    fn test_matrix_dims_ragged() {
+        let a = vec![vec![1, 2], vec![3, 4], vec![5]];
+        assert_eq!(
+            matrix_dims("a", &a),
+            Err(MatrixError::RaggedRows { operand: "a", row: 2, expected: 2, found: 1 })
+        );
+    }
+
+    #[test]
+    fn test_check_mul_dims() {
+        assert_eq!(
+            check_mul_dims("mul", ("a", (2, 3)), ("b", (3, 4)), Some(("c", (2, 4)))),
+            Ok(())
+        );
+        assert_eq!(
+            check_mul_dims("mul", ("a", (2, 3)), ("b", (2, 4)), None),
+            Err(MatrixError::ShapeMismatch {
+                op: "mul",
+                lhs: "a",
+                lhs_dims: (2, 3),
+                rhs: "b",
+                rhs_dims: (2, 4)
+            })
+        );
+        assert_eq!(
+            check_mul_dims("mul", ("a", (2, 3)), ("b", (3, 4)), Some(("c", (4, 2)))),
+            Err(MatrixError::ShapeMismatch {
+                op: "mul",
+                lhs: "c",
+                lhs_dims: (4, 2),
+                rhs: "product",
+                rhs_dims: (2, 4)
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_num_reps() {
+        assert_eq!(check_num_reps("verify_mul_batch", 1), Ok(()));
+        assert_eq!(
+            check_num_reps("verify_mul_batch", 0),
+            Err(MatrixError::ZeroRepetitions { op: "verify_mul_batch" })
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let err = MatrixError::RaggedRows { operand: "a", row: 2, expected: 2, found: 1 };
+        assert_eq!(err.to_string(), "matrix `a` is ragged: row 2 has 1 entries but row 0 has 2");
+        let err = MatrixError::ZeroRepetitions { op: "verify_mul_batch" };
+        assert_eq!(
+            err.to_string(),
+            "verify_mul_batch: the number of repetitions `num_reps` must be at least 1"
+        );
+    }
+}
@@ -2,16 +2,27 @@
 #![allow(dead_code)]
 // #[allow(unused_imports)]
 use halo2_base::gates::{GateChip, GateInstructions, RangeChip, RangeInstructions};
-use halo2_base::utils::{biguint_to_fe, BigPrimeField};
+use halo2_base::utils::{bigint_to_fe, biguint_to_fe, fe_to_bigint, fe_to_biguint, BigPrimeField};
 use halo2_base::{AssignedValue, QuantumCell};
 use halo2_base::{
     Context,
     QuantumCell::{Constant, Existing},
 };
-use num_bigint::BigUint;
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
 use poseidon::PoseidonChip;
+use std::cmp;
 use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
 
+mod error;
+pub use error::*;
+
+// T, R_F, R_P values correspond to POSEIDON-128 values given in Table 2 of the Poseidon hash paper
+const POSEIDON_T: usize = 3;
+const POSEIDON_RATE: usize = 2;
+const POSEIDON_R_F: usize = 8;
+const POSEIDON_R_P: usize = 57;
+
 #[derive(Clone)]
 /// ZKVector is always associated to a fixed point chip for which we need [PRECISION_BITS]
 pub struct ZkVector<F: BigPrimeField, const PRECISION_BITS: u32> {
@@ -189,9 +200,25 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> ZkVector<F, PRECISION_BITS> {
         }
     }
 
+    /// Constrains all the entries of the vector to be in (-2^max_bits, 2^max_bits)
+    /// according to the absolute value check in `check_abs_less_than`
+    pub fn entries_abs_less_than(
+        &self,
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        max_bits: usize,
+    ) {
+        let bnd = BigUint::from(2u32).pow(max_bits as u32);
+        for elem in &self.v {
+            check_abs_less_than(ctx, fpchip.range_gate(), *elem, &bnd);
+        }
+    }
+
     /// Assumes all entries of the vector are in [0, 2^max_bits) (fails silently otherwise)
     ///
     /// Constrains the entries to be in decreasing order
+    ///
+    /// For signed entries in (-2^b, 2^b) (see `entries_abs_less_than`), use `max_bits = b + 1`
     pub fn entries_in_desc_order(
         &self,
         ctx: &mut Context<F>,
@@ -211,6 +238,15 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> ZkVector<F, PRECISION_BITS> {
     }
 }
 
+/// Variants of the Freivalds check used to verify a claimed matrix product `c_s = a*b`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FreivaldsMode {
+    /// Checks `c_s*v = a*(b*v)` for a random vector `v`; one equality per row of `c_s`
+    RowWise,
+    /// Checks `x^T*c_s*y = (x^T*a)*(b*y)` for random vectors `x` and `y`; a single equality
+    TwoSided,
+}
+
 #[derive(Clone)]
 pub struct ZkMatrix<F: BigPrimeField, const PRECISION_BITS: u32> {
     pub matrix: Vec<Vec<AssignedValue<F>>>,
@@ -246,6 +282,27 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> ZkMatrix<F, PRECISION_BITS> {
         return Self { matrix: zkmatrix, num_rows: num_rows, num_col: num_col };
     }
 
+    /// Same as `new`, but returns an error instead of panicking if `matrix` is empty or ragged
+    pub fn try_new(
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        matrix: &Vec<Vec<f64>>,
+    ) -> Result<Self, MatrixError> {
+        matrix_dims("matrix", matrix)?;
+        return Ok(Self::new(ctx, fpchip, matrix));
+    }
+
+    /// Outputs the dimensions `(num_rows, num_col)` of the matrix after checking that its entries are well formed
+    /// and agree with `num_rows` and `num_col`; `operand` is the name used for the matrix in the error
+    pub fn checked_dims(&self, operand: &'static str) -> Result<(usize, usize), MatrixError> {
+        let actual = matrix_dims(operand, &self.matrix)?;
+        let stored = (self.num_rows, self.num_col);
+        if actual != stored {
+            return Err(MatrixError::InconsistentDims { operand, stored, actual });
+        }
+        return Ok(actual);
+    }
+
     /// Dequantizes the matrix and returns it;
     ///
     /// Action is not constrained in anyway
@@ -288,6 +345,11 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> ZkMatrix<F, PRECISION_BITS> {
     /// `init_rand`:  is the starting randomness/ challenge value; should commit to
     /// *at least* the matrices `a, b, c_s`
     ///
+    /// Constrains `c_s*v = a*(b*v)` for the random vector `v = (1, r, r^2, ..., r^(d-1))`;
+    /// the circuit is unsatisfiable if the check fails
+    ///
+    /// Performs a single repetition of the check; see `verify_mul_amplified` for the soundness error
+    ///
     /// Since, this method only verifies field multiplication, it will not fail even if
     /// `a` and `b` are incorrectly encoded. However, trying to rescale the result and use
     /// it downstream might fail in this case.
@@ -298,42 +360,252 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> ZkMatrix<F, PRECISION_BITS> {
         b: &Self,
         c_s: &Vec<Vec<AssignedValue<F>>>,
         init_rand: &AssignedValue<F>,
+    ) {
+        Self::verify_mul_amplified(ctx, fpchip, a, b, c_s, init_rand, 1);
+    }
+
+    /// Same as `verify_mul`, but repeats the check `num_reps` times with independent challenges
+    /// derived from `init_rand` using `freivalds_challenges`
+    ///
+    /// Each repetition tests a polynomial of degree `d-1` in the challenge, where `d = c_s[0].len()`,
+    /// so a wrong `c_s` passes with probability at most `((d-1)/|F|)^num_reps`;
+    /// use `freivalds_num_reps` to pick `num_reps` for a target soundness error and `freivalds_soundness_bits`
+    /// to report it
+    ///
+    /// Adds about `num_reps*(2*N*d + K*d)` cells for `a` of size `N X K`
+    pub fn verify_mul_amplified(
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        a: &Self,
+        b: &Self,
+        c_s: &Vec<Vec<AssignedValue<F>>>,
+        init_rand: &AssignedValue<F>,
+        num_reps: usize,
     ) {
         assert_eq!(a.num_col, b.num_rows);
         assert_eq!(c_s.len(), a.num_rows);
         assert_eq!(c_s[0].len(), b.num_col);
         assert!(c_s[0].len() >= 1);
+        assert!(num_reps >= 1);
 
         let d = c_s[0].len();
         let gate = fpchip.gate();
 
-        // v = (1, r, r^2, ..., r^(d-1)) where r = init_rand is the random challenge value
-        let mut v: Vec<AssignedValue<F>> = Vec::new();
+        for r in freivalds_challenges(ctx, gate, init_rand, num_reps) {
+            // v = (1, r, r^2, ..., r^(d-1)) where r is the random challenge value for this repetition
+            let v = challenge_powers(ctx, gate, &r, d);
 
-        let one = ctx.load_witness(F::one());
-        gate.assert_is_const(ctx, &one, &F::one());
-        v.push(one);
+            let cs_times_v = field_mat_vec_mul(ctx, gate, c_s, &v);
+            let b_times_v = field_mat_vec_mul(ctx, gate, &b.matrix, &v);
+            let ab_times_v = field_mat_vec_mul(ctx, gate, &a.matrix, &b_times_v);
 
-        for i in 1..d {
-            let prev = &v[i - 1];
-            let r_to_i = fpchip.gate().mul(ctx, *prev, *init_rand);
-            v.push(r_to_i);
+            for i in 0..cs_times_v.len() {
+                ctx.constrain_equal(&cs_times_v[i], &ab_times_v[i]);
+            }
         }
-        let v = v;
+    }
 
-        // println!("Random vector, v = [");
-        // for x in &v {
-        //     println!("{:?}", *x.value());
-        // }
-        // println!("]");
+    /// Same as `verify_mul_amplified`, but performs the two-sided version of the Freivalds check: for challenges `r` and `s`,
+    /// with `y = (1, r, ..., r^(d-1))` and `x = (1, s, ..., s^(N-1))`, constrains `x^T*c_s*y = (x^T*a)*(b*y)`
+    ///
+    /// Only a single equality is constrained per repetition (instead of one per row of `c_s`), which is useful for tall `c_s`
+    ///
+    /// Each repetition tests a polynomial of total degree `(N-1) + (d-1)` for `c_s` of size `N X d`; see `freivalds_soundness_bits`
+    ///
+    /// Adds about `num_reps*(2*N*d + N*K + K*d)` cells for `a` of size `N X K`
+    pub fn verify_mul_two_sided(
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        a: &Self,
+        b: &Self,
+        c_s: &Vec<Vec<AssignedValue<F>>>,
+        init_rand: &AssignedValue<F>,
+        num_reps: usize,
+    ) {
+        assert_eq!(a.num_col, b.num_rows);
+        assert_eq!(c_s.len(), a.num_rows);
+        assert_eq!(c_s[0].len(), b.num_col);
+        assert!(c_s[0].len() >= 1);
+        assert!(num_reps >= 1);
 
-        let cs_times_v = field_mat_vec_mul(ctx, gate, c_s, &v);
-        let b_times_v = field_mat_vec_mul(ctx, gate, &b.matrix, &v);
-        let ab_times_v = field_mat_vec_mul(ctx, gate, &a.matrix, &b_times_v);
+        let d = c_s[0].len();
+        let gate = fpchip.gate();
+        let a_t = Self::transpose_matrix(a);
+        let challenges = freivalds_challenges(ctx, gate, init_rand, 2 * num_reps);
+
+        for rep in 0..num_reps {
+            let y = challenge_powers(ctx, gate, &challenges[2 * rep], d);
+            let x = challenge_powers(ctx, gate, &challenges[2 * rep + 1], c_s.len());
+
+            let cs_times_y = field_mat_vec_mul(ctx, gate, c_s, &y);
+            let lhs = gate.inner_product(
+                ctx,
+                cs_times_y,
+                x.iter().map(|elem| Existing(*elem)).collect::<Vec<_>>(),
+            );
+
+            let b_times_y = field_mat_vec_mul(ctx, gate, &b.matrix, &y);
+            let xt_times_a = field_mat_vec_mul(ctx, gate, &a_t.matrix, &x);
+            let rhs = gate.inner_product(
+                ctx,
+                xt_times_a,
+                b_times_y.iter().map(|elem| Existing(*elem)).collect::<Vec<_>>(),
+            );
+
+            ctx.constrain_equal(&lhs, &rhs);
+        }
+    }
+
+    /// Checks if `a*b = c_s` in field multiplication using the Freivalds check selected by `mode`;
+    /// see `verify_mul_amplified` and `verify_mul_two_sided`
+    pub fn verify_mul_with_mode(
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        a: &Self,
+        b: &Self,
+        c_s: &Vec<Vec<AssignedValue<F>>>,
+        init_rand: &AssignedValue<F>,
+        mode: FreivaldsMode,
+        num_reps: usize,
+    ) {
+        match mode {
+            FreivaldsMode::RowWise => {
+                Self::verify_mul_amplified(ctx, fpchip, a, b, c_s, init_rand, num_reps)
+            }
+            FreivaldsMode::TwoSided => {
+                Self::verify_mul_two_sided(ctx, fpchip, a, b, c_s, init_rand, num_reps)
+            }
+        }
+    }
+
+    /// Checks a list of claimed products at once: for each triple `(a, b, c_s)` in `products`, checks
+    /// that `a*b = c_s` in field multiplication (see `verify_mul`)
+    ///
+    /// For a challenge `r`, each triple is reduced to the vector `c_s*v - a*(b*v)` with `v = (1, r, ..., r^(d-1))`;
+    /// the powers of `r` are computed once (for the largest `d`) and shared by all triples. The reduced vectors of all
+    /// triples are then folded into a single scalar with the powers of a second challenge `s`, which is constrained to be zero
+    ///
+    /// `init_rand` must commit to *at least* all the matrices in `products`; both challenges of each of the `num_reps`
+    /// repetitions are derived from it using `freivalds_challenges`
+    ///
+    /// Each repetition tests a polynomial of total degree `(d_max-1) + (R-1)`, where `d_max` is the largest
+    /// number of columns and `R` is the total number of rows of the `c_s`; see `freivalds_soundness_bits`
+    ///
+    /// Adds about `2*N*d + K*d` cells per triple (`a` of size `N X K`) and `2*R` cells for the folding in each repetition,
+    /// with a single equality constraint
+    pub fn verify_mul_batch(
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        products: &Vec<(&Self, &Self, &Vec<Vec<AssignedValue<F>>>)>,
+        init_rand: &AssignedValue<F>,
+        num_reps: usize,
+    ) {
+        assert!(products.len() >= 1);
+        assert!(num_reps >= 1);
+        let mut max_d = 0;
+        for (a, b, c_s) in products {
+            assert_eq!(a.num_col, b.num_rows);
+            assert_eq!(c_s.len(), a.num_rows);
+            assert_eq!(c_s[0].len(), b.num_col);
+            assert!(c_s[0].len() >= 1);
+            max_d = cmp::max(max_d, c_s[0].len());
+        }
+
+        let gate = fpchip.gate();
+        let challenges = freivalds_challenges(ctx, gate, init_rand, 2 * num_reps);
+
+        for rep in 0..num_reps {
+            let r = &challenges[2 * rep];
+            let s = &challenges[2 * rep + 1];
+
+            // v = (1, r, r^2, ..., r^(max_d-1)); triples with fewer columns use a prefix of v
+            let v = challenge_powers(ctx, gate, r, max_d);
+
+            let mut lhs: Vec<AssignedValue<F>> = Vec::new();
+            let mut rhs: Vec<AssignedValue<F>> = Vec::new();
+            for (a, b, c_s) in products {
+                let v_d = v[..c_s[0].len()].to_vec();
+                let cs_times_v = field_mat_vec_mul(ctx, gate, c_s, &v_d);
+                let b_times_v = field_mat_vec_mul(ctx, gate, &b.matrix, &v_d);
+                let ab_times_v = field_mat_vec_mul(ctx, gate, &a.matrix, &b_times_v);
+                lhs.extend(cs_times_v);
+                rhs.extend(ab_times_v);
+            }
+
+            // fold all the rows with w = (1, s, s^2, ..., s^(R-1))
+            let w = challenge_powers(ctx, gate, s, lhs.len());
+            let lhs_folded =
+                gate.inner_product(ctx, lhs, w.iter().map(|x| Existing(*x)).collect::<Vec<_>>());
+            let rhs_folded =
+                gate.inner_product(ctx, rhs, w.iter().map(|x| Existing(*x)).collect::<Vec<_>>());
+            ctx.constrain_equal(&lhs_folded, &rhs_folded);
+        }
+    }
+
+    /// Same as `verify_mul`, but returns an error instead of panicking if `a`, `b` and `c_s` do not have
+    /// compatible dimensions
+    pub fn try_verify_mul(
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        a: &Self,
+        b: &Self,
+        c_s: &Vec<Vec<AssignedValue<F>>>,
+        init_rand: &AssignedValue<F>,
+    ) -> Result<(), MatrixError> {
+        Self::check_product_dims("verify_mul", a, b, c_s)?;
+        Self::verify_mul(ctx, fpchip, a, b, c_s, init_rand);
+        return Ok(());
+    }
+
+    /// Same as `verify_mul_with_mode`, but returns an error instead of panicking if `a`, `b` and `c_s` do not have
+    /// compatible dimensions or `num_reps` is zero
+    pub fn try_verify_mul_with_mode(
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        a: &Self,
+        b: &Self,
+        c_s: &Vec<Vec<AssignedValue<F>>>,
+        init_rand: &AssignedValue<F>,
+        mode: FreivaldsMode,
+        num_reps: usize,
+    ) -> Result<(), MatrixError> {
+        check_num_reps("verify_mul_with_mode", num_reps)?;
+        Self::check_product_dims("verify_mul_with_mode", a, b, c_s)?;
+        Self::verify_mul_with_mode(ctx, fpchip, a, b, c_s, init_rand, mode, num_reps);
+        return Ok(());
+    }
 
-        for i in 0..cs_times_v.len() {
-            gate.is_equal(ctx, cs_times_v[i], ab_times_v[i]);
+    /// Same as `verify_mul_batch`, but returns an error instead of panicking if any of the triples
+    /// does not have compatible dimensions or `num_reps` is zero
+    pub fn try_verify_mul_batch(
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        products: &Vec<(&Self, &Self, &Vec<Vec<AssignedValue<F>>>)>,
+        init_rand: &AssignedValue<F>,
+        num_reps: usize,
+    ) -> Result<(), MatrixError> {
+        check_num_reps("verify_mul_batch", num_reps)?;
+        if products.is_empty() {
+            return Err(MatrixError::Empty { operand: "products" });
+        }
+        for (a, b, c_s) in products {
+            Self::check_product_dims("verify_mul_batch", a, b, c_s)?;
         }
+        Self::verify_mul_batch(ctx, fpchip, products, init_rand, num_reps);
+        return Ok(());
+    }
+
+    /// Checks that `c_s` can be the product of `a` and `b`
+    fn check_product_dims(
+        op: &'static str,
+        a: &Self,
+        b: &Self,
+        c_s: &Vec<Vec<AssignedValue<F>>>,
+    ) -> Result<(), MatrixError> {
+        let a_dims = a.checked_dims("a")?;
+        let b_dims = b.checked_dims("b")?;
+        let c_dims = matrix_dims("c_s", c_s)?;
+        return check_mul_dims(op, ("a", a_dims), ("b", b_dims), Some(("c_s", c_dims)));
     }
 
     /// Takes `c_s` and divides it by the quantization factor to scale it;
@@ -374,14 +646,10 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> ZkMatrix<F, PRECISION_BITS> {
         gate: &GateChip<F>,
         matrix_list: &Vec<Self>,
     ) -> AssignedValue<F> {
-        // T, R_F, R_P values correspond to POSEIDON-128 values given in Table 2 of the Poseidon hash paper
-        const T: usize = 3;
-        const RATE: usize = 2;
-        const R_F: usize = 8;
-        const R_P: usize = 57;
-
         // MODE OF USE: we will update the poseidon chip with all the values and then extract one value
-        let mut poseidon = PoseidonChip::<F, T, RATE>::new(ctx, R_F, R_P).unwrap();
+        let mut poseidon =
+            PoseidonChip::<F, POSEIDON_T, POSEIDON_RATE>::new(ctx, POSEIDON_R_F, POSEIDON_R_P)
+                .unwrap();
         for mat in matrix_list {
             for row in &mat.matrix {
                 poseidon.update(row);
@@ -392,6 +660,100 @@ impl<F: BigPrimeField, const PRECISION_BITS: u32> ZkMatrix<F, PRECISION_BITS> {
         return init_rand;
     }
 
+    /// With zk constraints calculates the trace of the matrix, i.e., the sum of its diagonal entries
+    ///
+    /// Asserts (outside of circuit) that the matrix is square; leads to about `N` constraints
+    pub fn trace(
+        &self,
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    ) -> AssignedValue<F> {
+        assert_eq!(self.num_rows, self.num_col);
+        let diag: Vec<AssignedValue<F>> = (0..self.num_rows).map(|i| self.matrix[i][i]).collect();
+        return fpchip.gate().sum(ctx, diag);
+    }
+
+    /// With zk constraints calculates the square of the Frobenius norm of the matrix, i.e., the sum of squares of its entries
+    ///
+    /// As in `ZkVector::inner_product`, the sum is computed in the field and rescaled once
+    ///
+    /// Leads to about `N*M + 90` constraints
+    pub fn frobenius_norm_square(
+        &self,
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    ) -> AssignedValue<F> {
+        let entries: Vec<AssignedValue<F>> = self.matrix.iter().flatten().copied().collect();
+        let norm_sq_s =
+            fpchip.gate().inner_product(ctx, entries.clone(), entries.iter().map(|x| Existing(*x)));
+        let (norm_sq, _) = fpchip.signed_div_scale(ctx, norm_sq_s);
+        return norm_sq;
+    }
+
+    /// With zk constraints calculates the entrywise max norm of the matrix, i.e., `max |a[i][j]|`
+    ///
+    /// Also constrains every entry to satisfy `|a[i][j]| < 2^max_bits` (as quantized values), which is needed for the comparisons
+    ///
+    /// Leads to one `signed_abs` and one step of `max_of_nonneg` per entry, i.e., about `2*N*M` comparisons on `max_bits + 1` bits
+    pub fn max_abs_norm(
+        &self,
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        max_bits: usize,
+    ) -> AssignedValue<F> {
+        let range = fpchip.range_gate();
+        let mut abs_entries: Vec<AssignedValue<F>> = Vec::new();
+        for row in &self.matrix {
+            for elem in row {
+                abs_entries.push(signed_abs(ctx, range, *elem, max_bits));
+            }
+        }
+        return max_of_nonneg(ctx, range, &abs_entries, max_bits);
+    }
+
+    /// With zk constraints calculates the induced 1-norm of the matrix, i.e., the maximum over the columns of the sum of
+    /// absolute values of the column entries
+    ///
+    /// Also constrains every entry to satisfy `|a[i][j]| < 2^max_bits` (as quantized values), which is needed for the comparisons
+    ///
+    /// Leads to one `signed_abs` per entry and `M` steps of `max_of_nonneg` on `max_bits + log2(N)` bits, i.e., about `N*M + M` comparisons
+    pub fn one_norm(
+        &self,
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        max_bits: usize,
+    ) -> AssignedValue<F> {
+        let a_t = Self::transpose_matrix(self);
+        return a_t.inf_norm(ctx, fpchip, max_bits);
+    }
+
+    /// With zk constraints calculates the induced infinity-norm of the matrix, i.e., the maximum over the rows of the sum of
+    /// absolute values of the row entries
+    ///
+    /// Also constrains every entry to satisfy `|a[i][j]| < 2^max_bits` (as quantized values), which is needed for the comparisons
+    ///
+    /// Leads to one `signed_abs` per entry and `N` steps of `max_of_nonneg` on `max_bits + log2(M)` bits, i.e., about `N*M + N` comparisons
+    pub fn inf_norm(
+        &self,
+        ctx: &mut Context<F>,
+        fpchip: &FixedPointChip<F, PRECISION_BITS>,
+        max_bits: usize,
+    ) -> AssignedValue<F> {
+        let range = fpchip.range_gate();
+        let gate = fpchip.gate();
+        let mut row_sums: Vec<AssignedValue<F>> = Vec::new();
+        for row in &self.matrix {
+            let mut abs_row: Vec<AssignedValue<F>> = Vec::new();
+            for elem in row {
+                abs_row.push(signed_abs(ctx, range, *elem, max_bits));
+            }
+            row_sums.push(gate.sum(ctx, abs_row));
+        }
+        // each row sum is less than num_col*2^max_bits
+        let sum_bits = max_bits + bit_len(self.num_col) as usize;
+        return max_of_nonneg(ctx, range, &row_sums, sum_bits);
+    }
+
     /// Outputs the transpose matrix of a matrix `a`;
     ///
     /// Doesn't create any new constraints; just outputs the a copy of the transposed Self.matrix
@@ -424,6 +786,180 @@ pub fn check_abs_less_than<F: BigPrimeField>(
     range.check_big_less_than_safe(ctx, translated_x, new_bnd);
 }
 
+/// Outputs the boolean `x < 0` for a signed fixed point (or field) element `x`, where negative numbers are represented
+/// by their field negatives
+///
+/// Constrains `|x| < 2^max_bits` with `check_abs_less_than`, so that `x + 2^max_bits - 1` is in `[0, 2^(max_bits+1) - 1)`;
+/// the sign is then obtained by comparing `x + 2^max_bits - 1` with `2^max_bits - 1`
+pub fn is_negative<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    max_bits: usize,
+) -> AssignedValue<F> {
+    let bnd = BigUint::from(2u32).pow(max_bits as u32);
+    check_abs_less_than(ctx, range, x, &bnd);
+    let offset: F = biguint_to_fe(&(bnd - BigUint::from(1u32)));
+    let shifted_x = range.gate.add(ctx, x, Constant(offset));
+    return range.is_less_than(ctx, shifted_x, Constant(offset), max_bits + 1);
+}
+
+/// Outputs the absolute value `|x|` of a signed fixed point (or field) element `x`
+///
+/// Constrains `|x| < 2^max_bits`; see `is_negative`
+pub fn signed_abs<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    max_bits: usize,
+) -> AssignedValue<F> {
+    let is_neg = is_negative(ctx, range, x, max_bits);
+    let neg_x = range.gate.neg(ctx, x);
+    return range.gate.select(ctx, neg_x, x, is_neg);
+}
+
+/// Number of bits needed to represent `n`
+pub(crate) fn bit_len(n: usize) -> u32 {
+    return usize::BITS - n.leading_zeros();
+}
+
+/// Outputs the maximum of the non-negative elements `xs`
+///
+/// Assumes all the elements are in `[0, 2^max_bits)` (fails silently otherwise)
+///
+/// Uses one comparison and one select per element
+pub fn max_of_nonneg<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    xs: &Vec<AssignedValue<F>>,
+    max_bits: usize,
+) -> AssignedValue<F> {
+    assert!(xs.len() > 0);
+    let mut max = xs[0];
+    for x in &xs[1..] {
+        let is_larger = range.is_less_than(ctx, max, *x, max_bits);
+        max = range.gate.select(ctx, *x, max, is_larger);
+    }
+    return max;
+}
+
+/// With zk constraints calculates the fixed point quotient `num/den` of the fixed point numbers `num` and `den`
+/// rounded to the nearest multiple of `2^-PRECISION_BITS`
+///
+/// The quotient `q` is a witness, which is determined uniquely by `check_div_quotient`; see there for the constraints on `num`,
+/// `den` and `max_bits`
+///
+/// Leads to four range checks of about `max_bits + PRECISION_BITS` bits, which is cheaper than a rescaling followed by a division
+pub fn verified_div<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    num: AssignedValue<F>,
+    den: AssignedValue<F>,
+    max_bits: usize,
+) -> AssignedValue<F> {
+    // witness generation: q = floor((2*num*2^P + den)/(2*den))
+    let num_int = fe_to_bigint(num.value());
+    let den_int = fe_to_bigint(den.value());
+    let q_int = if den_int > BigInt::from(0) {
+        let two = BigInt::from(2);
+        (&two * &num_int * BigInt::from(2u64.pow(PRECISION_BITS)) + &den_int)
+            .div_floor(&(&two * &den_int))
+    } else {
+        // no valid quotient exists; the constraints fail
+        BigInt::from(0)
+    };
+    let q = ctx.load_witness(bigint_to_fe(&q_int));
+
+    check_div_quotient(ctx, fpchip, num, den, q, max_bits);
+    return q;
+}
+
+/// Constrains the fixed point number `q` to be the quotient `num/den` of the fixed point numbers `num` and `den` rounded to the
+/// nearest multiple of `2^-PRECISION_BITS`, by `-den < 2*(q*den - num*2^PRECISION_BITS) <= den`; this determines `q` uniquely
+/// (ties are rounded up)
+///
+/// Constrains `|num| < 2^max_bits`, `1 <= den <= 2^max_bits` and `|q| < 2^(max_bits + PRECISION_BITS)` (as quantized values),
+/// so `den` must be strictly positive; `2*max_bits + PRECISION_BITS + 2` must be less than the number of bits of the field
+pub fn check_div_quotient<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    num: AssignedValue<F>,
+    den: AssignedValue<F>,
+    q: AssignedValue<F>,
+    max_bits: usize,
+) {
+    let range = fpchip.range_gate();
+    let gate = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    let bnd = BigUint::from(2u32).pow(max_bits as u32);
+    check_abs_less_than(ctx, range, num, &bnd);
+    let den_minus_one = gate.sub(ctx, den, Constant(F::one()));
+    range.range_check(ctx, den_minus_one, max_bits);
+    let q_bnd = BigUint::from(2u32).pow(max_bits as u32 + PRECISION_BITS);
+    check_abs_less_than(ctx, range, q, &q_bnd);
+
+    // t = den - 2*(q*den - num*2^P) should be in [0, 2*den)
+    let den_plus_num_s = gate.mul_add(ctx, num, Constant(quant + quant), den);
+    let q_den = gate.mul(ctx, q, den);
+    let t = gate.mul_add(ctx, q_den, Constant(-F::from(2)), den_plus_num_s);
+    range.range_check(ctx, t, max_bits + 2);
+    let two_den = gate.add(ctx, den, den);
+    range.check_less_than(ctx, t, two_den, max_bits + 2);
+}
+
+/// With zk constraints calculates the fixed point square root of the non-negative fixed point number `x`, rounded down to a
+/// multiple of `2^-PRECISION_BITS`
+///
+/// The root `s` is a witness, which is determined uniquely by `check_sqrt_root`
+///
+/// Constrains `x` to be in `[0, 2^max_bits)` (as a quantized value)
+///
+/// Leads to four range checks of at most `max_bits` bits, which is much cheaper than `qsqrt`
+pub fn verified_sqrt<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: AssignedValue<F>,
+    max_bits: usize,
+) -> AssignedValue<F> {
+    // witness generation: s = floor(sqrt(x*2^P))
+    let x_s_int = fe_to_biguint(x.value()) * BigUint::from(2u64.pow(PRECISION_BITS));
+    let s = ctx.load_witness(biguint_to_fe(&x_s_int.sqrt()));
+
+    check_sqrt_root(ctx, fpchip, x, s, max_bits);
+    return s;
+}
+
+/// Constrains the fixed point number `s` to be the square root of the fixed point number `x` rounded down to a multiple of
+/// `2^-PRECISION_BITS`, by `s^2 <= x*2^PRECISION_BITS < (s+1)^2`
+///
+/// Constrains `x` to be in `[0, 2^max_bits)` (as a quantized value)
+pub fn check_sqrt_root<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: AssignedValue<F>,
+    s: AssignedValue<F>,
+    max_bits: usize,
+) {
+    let range = fpchip.range_gate();
+    let gate = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    range.range_check(ctx, x, max_bits);
+    // x*2^P < 2^(max_bits + P), so s < 2^half_bits
+    let half_bits = (max_bits + PRECISION_BITS as usize + 1) / 2;
+    range.range_check(ctx, s, half_bits);
+
+    // lo = x*2^P - s^2 and hi = s^2 + 2*s - x*2^P are both in [0, 2*s]
+    let s_sq = gate.mul(ctx, s, s);
+    let x_s = gate.mul(ctx, x, Constant(quant));
+    let lo = gate.sub(ctx, x_s, s_sq);
+    range.range_check(ctx, lo, half_bits + 1);
+    let two_s = gate.add(ctx, s, s);
+    let hi = gate.sub(ctx, two_s, lo);
+    range.range_check(ctx, hi, half_bits + 1);
+}
+
 /// Takes as two matrices `a` and `b` as input and checks that `|a[i][j] - b[i][j]| < tol` for each `i,j`
 /// according to the absolute value check in `check_abs_less_than`
 ///
@@ -446,6 +982,30 @@ pub fn check_mat_diff<F: BigPrimeField>(
     }
 }
 
+/// Same as `check_mat_diff`, but returns an error instead of panicking if `a` and `b` are not well formed
+/// matrices of the same dimensions
+pub fn try_check_mat_diff<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    a: &Vec<Vec<AssignedValue<F>>>,
+    b: &Vec<Vec<AssignedValue<F>>>,
+    tol: &BigUint,
+) -> Result<(), MatrixError> {
+    let a_dims = matrix_dims("a", a)?;
+    let b_dims = matrix_dims("b", b)?;
+    if a_dims != b_dims {
+        return Err(MatrixError::ShapeMismatch {
+            op: "check_mat_diff",
+            lhs: "a",
+            lhs_dims: a_dims,
+            rhs: "b",
+            rhs_dims: b_dims,
+        });
+    }
+    check_mat_diff(ctx, range, a, b, tol);
+    return Ok(());
+}
+
 /// Given a matrix of field elements `a` and a field element `scalar_id`, checks that `|a[i][j] - scalar_id*Id[i][j]| < tol` for each `i,j`, where Id is the identity matrix
 /// according to the absolute value check in `check_abs_less_than`
 pub fn check_mat_id<F: BigPrimeField>(
@@ -556,6 +1116,19 @@ pub fn honest_prover_mat_mul<F: BigPrimeField>(
     return assigned_c_s;
 }
 
+/// Same as `honest_prover_mat_mul`, but returns an error instead of panicking if `a` and `b` are not well formed
+/// or cannot be multiplied
+pub fn try_honest_prover_mat_mul<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    a: &Vec<Vec<AssignedValue<F>>>,
+    b: &Vec<Vec<AssignedValue<F>>>,
+) -> Result<Vec<Vec<AssignedValue<F>>>, MatrixError> {
+    let a_dims = matrix_dims("a", a)?;
+    let b_dims = matrix_dims("b", b)?;
+    check_mul_dims("honest_prover_mat_mul", ("a", a_dims), ("b", b_dims), None)?;
+    return Ok(honest_prover_mat_mul(ctx, a, b));
+}
+
 /// Multiplies matrix `a` to vector `v` in the zk-circuit and returns the constrained output `a.v`
 /// -- all assuming `a` and `v` are field elements (and not fixed point encoded)
 ///
@@ -587,6 +1160,27 @@ pub fn field_mat_vec_mul<F: BigPrimeField>(
     return y;
 }
 
+/// Same as `field_mat_vec_mul`, but returns an error instead of panicking if `a` is not well formed
+/// or cannot be multiplied to `v`
+pub fn try_field_mat_vec_mul<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    a: &Vec<Vec<AssignedValue<F>>>,
+    v: &Vec<AssignedValue<F>>,
+) -> Result<Vec<AssignedValue<F>>, MatrixError> {
+    let dims = matrix_dims("a", a)?;
+    if dims.1 != v.len() {
+        return Err(MatrixError::VectorLengthMismatch {
+            op: "field_mat_vec_mul",
+            matrix: "a",
+            dims,
+            vector: "v",
+            len: v.len(),
+        });
+    }
+    return Ok(field_mat_vec_mul(ctx, gate, a, v));
+}
+
 /// Multiplies matrix `a` by a diagonal matrix represented as a vector `v` in the zk-circuit and returns the constrained output `a*Diag(v)`
 /// -- all assuming `a` and `v` are field elements, (and not fixed point encoded)
 ///
@@ -614,3 +1208,739 @@ pub fn mat_times_diag_mat<F: BigPrimeField>(
     }
     return m;
 }
+
+/// Takes as input a vector `v` and a matrix `a` and outputs `Diag(v)*a`, i.e., scales row `i` of `a` by `v[i]`
+/// -- all assuming `a` and `v` consist of field elements (and not fixed point encoded)
+///
+/// Assumes matrix `a` is well defined (rows are equal size) and asserts (outside of circuit) that `v` has one entry per row of `a`
+pub fn diag_mat_times_mat<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    v: &Vec<AssignedValue<F>>,
+    a: &Vec<Vec<AssignedValue<F>>>,
+) -> Vec<Vec<AssignedValue<F>>> {
+    assert_eq!(v.len(), a.len());
+    let mut m: Vec<Vec<AssignedValue<F>>> = Vec::new();
+    for i in 0..a.len() {
+        let mut new_row: Vec<AssignedValue<F>> = Vec::new();
+        for j in 0..a[i].len() {
+            let prod = gate.mul(ctx, v[i], a[i][j]);
+            new_row.push(prod);
+        }
+        m.push(new_row);
+    }
+    return m;
+}
+
+/// Multiplies each entry of the matrix `a` by the constant `scalar` in the zk-circuit and returns the constrained output
+/// -- all assuming `a` is a matrix of field elements (and not fixed point encoded)
+///
+/// Useful for bringing a fixed point matrix to the scale of an unscaled product (`scalar = 2^PRECISION_BITS`)
+pub fn field_mat_scalar_mul<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    a: &Vec<Vec<AssignedValue<F>>>,
+    scalar: F,
+) -> Vec<Vec<AssignedValue<F>>> {
+    let mut m: Vec<Vec<AssignedValue<F>>> = Vec::new();
+    for row in a {
+        let mut new_row: Vec<AssignedValue<F>> = Vec::new();
+        for elem in row {
+            new_row.push(gate.mul(ctx, *elem, Constant(scalar)));
+        }
+        m.push(new_row);
+    }
+    return m;
+}
+
+/// Constrains all the entries of the matrix `a` below the diagonal to be zero, i.e., `a` is upper triangular
+pub fn check_upper_triangular<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    a: &Vec<Vec<AssignedValue<F>>>,
+) {
+    for i in 0..a.len() {
+        for j in 0..cmp::min(i, a[i].len()) {
+            gate.assert_is_const(ctx, &a[i][j], &F::zero());
+        }
+    }
+}
+
+/// Constrains all the entries of the matrix `a` above the diagonal to be zero, i.e., `a` is lower triangular
+pub fn check_lower_triangular<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    a: &Vec<Vec<AssignedValue<F>>>,
+) {
+    for i in 0..a.len() {
+        for j in (i + 1)..a[i].len() {
+            gate.assert_is_const(ctx, &a[i][j], &F::zero());
+        }
+    }
+}
+
+/// Constrains the square matrix `a` to be symmetric, i.e., `a[i][j] = a[j][i]` for each `i,j`
+///
+/// Only adds equality constraints (no new cells)
+pub fn check_mat_symmetric<F: BigPrimeField>(ctx: &mut Context<F>, a: &Vec<Vec<AssignedValue<F>>>) {
+    for i in 0..a.len() {
+        assert_eq!(a[i].len(), a.len());
+        for j in (i + 1)..a.len() {
+            ctx.constrain_equal(&a[i][j], &a[j][i]);
+        }
+    }
+}
+
+/// Same as `mat_times_diag_mat`, but returns an error instead of panicking if `a` is not well formed
+/// or has fewer columns than the length of `v`
+pub fn try_mat_times_diag_mat<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    a: &Vec<Vec<AssignedValue<F>>>,
+    v: &Vec<AssignedValue<F>>,
+) -> Result<Vec<Vec<AssignedValue<F>>>, MatrixError> {
+    let dims = matrix_dims("a", a)?;
+    if v.len() > dims.1 {
+        return Err(MatrixError::VectorLengthMismatch {
+            op: "mat_times_diag_mat",
+            matrix: "a",
+            dims,
+            vector: "v",
+            len: v.len(),
+        });
+    }
+    return Ok(mat_times_diag_mat(ctx, gate, a, v));
+}
+
+/// Derives `num_reps` challenges for repeated Freivalds checks from the challenge `init_rand`;
+///
+/// The first challenge is `init_rand` itself and the rest are squeezed in circuit out of a Poseidon sponge which
+/// has absorbed `init_rand`; modelling Poseidon as a random oracle, they are independent and uniformly random
+pub fn freivalds_challenges<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) -> Vec<AssignedValue<F>> {
+    let mut challenges: Vec<AssignedValue<F>> = vec![*init_rand];
+    if num_reps > 1 {
+        let mut poseidon =
+            PoseidonChip::<F, POSEIDON_T, POSEIDON_RATE>::new(ctx, POSEIDON_R_F, POSEIDON_R_P)
+                .unwrap();
+        poseidon.update(&[*init_rand]);
+        for _ in 1..num_reps {
+            challenges.push(poseidon.squeeze(ctx, gate).unwrap());
+        }
+    }
+    return challenges;
+}
+
+/// Outputs the constrained vector `(1, r, r^2, ..., r^(d-1))`
+fn challenge_powers<F: BigPrimeField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    r: &AssignedValue<F>,
+    d: usize,
+) -> Vec<AssignedValue<F>> {
+    let mut v: Vec<AssignedValue<F>> = Vec::new();
+
+    let one = ctx.load_witness(F::one());
+    gate.assert_is_const(ctx, &one, &F::one());
+    v.push(one);
+
+    for i in 1..d {
+        let prev = &v[i - 1];
+        let r_to_i = gate.mul(ctx, *prev, *r);
+        v.push(r_to_i);
+    }
+    return v;
+}
+
+/// Soundness error (in bits) of `num_reps` repetitions of a Freivalds check which tests a polynomial identity
+/// of total degree `degree` in the challenges, i.e., the check fails to catch a wrong product with probability at most
+/// `2^-freivalds_soundness_bits(degree, num_reps)`
+///
+/// For `verify_mul_amplified`, `degree = c_s[0].len() - 1`; for `verify_mul_two_sided`, `degree = c_s.len() + c_s[0].len() - 2`;
+/// for `verify_mul_batch`, see its documentation
+///
+/// Uses `|F| >= 2^(F::NUM_BITS - 1)`; returns infinity for `degree = 0`, in which case the check is exact
+///
+/// All the challenges are derived from the single challenge `init_rand` (see `freivalds_challenges`), so the output is capped at
+/// `freivalds_max_soundness_bits`: the set of bad values of `init_rand` is either empty or has probability at least `1/|F|`
+pub fn freivalds_soundness_bits<F: BigPrimeField>(degree: usize, num_reps: usize) -> f64 {
+    if degree == 0 {
+        return f64::INFINITY;
+    }
+    let bits_per_rep = (F::NUM_BITS - 1) as f64 - (degree as f64).log2();
+    let bits = bits_per_rep * (num_reps as f64);
+    return bits.min(freivalds_max_soundness_bits::<F>() as f64);
+}
+
+/// Largest soundness error (in bits) which can be claimed for Freivalds checks whose challenges are derived from a single
+/// challenge `init_rand`, i.e., the entropy `F::NUM_BITS - 1` of `init_rand`; more repetitions cannot push the soundness error
+/// below `1/|F|`
+pub fn freivalds_max_soundness_bits<F: BigPrimeField>() -> u32 {
+    return F::NUM_BITS - 1;
+}
+
+/// Smallest number of repetitions of a Freivalds check testing a polynomial identity of total degree `degree`
+/// so that its soundness error is at most `2^-target_bits` (see `freivalds_soundness_bits`)
+///
+/// Panics if `target_bits` is larger than `freivalds_max_soundness_bits`, which no number of repetitions can achieve
+pub fn freivalds_num_reps<F: BigPrimeField>(target_bits: u32, degree: usize) -> usize {
+    if degree == 0 {
+        return 1;
+    }
+    assert!(
+        target_bits <= freivalds_max_soundness_bits::<F>(),
+        "target_bits exceeds the entropy of the challenge init_rand"
+    );
+    let bits_per_rep = (F::NUM_BITS - 1) as f64 - (degree as f64).log2();
+    assert!(bits_per_rep > 0.0, "degree is too large for the field");
+    let num_reps = ((target_bits as f64) / bits_per_rep).ceil() as usize;
+    return cmp::max(num_reps, 1);
+}
+
+#[cfg(test)]
+mod test {
+    use super::test_utils::{forge_last_entry, mock_accepts, mock_init_rand};
+    use super::*;
+    use halo2_base::gates::builder::GateThreadBuilder;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    const PRECISION_BITS: u32 = 32;
+
+    /// Loads `a` (`2 X 3`), `b` (`3 X 2`) and their unscaled product `c_s`; if `forge` is set, the last entry of `c_s` is forged
+    fn mul_example(
+        ctx: &mut Context<Fr>,
+        fpchip: &FixedPointChip<Fr, PRECISION_BITS>,
+        forge: bool,
+    ) -> (ZkMatrix<Fr, PRECISION_BITS>, ZkMatrix<Fr, PRECISION_BITS>, Vec<Vec<AssignedValue<Fr>>>)
+    {
+        let a = ZkMatrix::new(ctx, fpchip, &vec![vec![1.0, 2.0, -0.5], vec![3.0, 4.0, 0.25]]);
+        let b =
+            ZkMatrix::new(ctx, fpchip, &vec![vec![1.0, 0.5], vec![-1.0, 2.0], vec![0.75, -3.0]]);
+        let c_s = honest_prover_mat_mul(ctx, &a.matrix, &b.matrix);
+        let c_s = if forge { forge_last_entry(ctx, &c_s) } else { c_s };
+        return (a, b, c_s);
+    }
+
+    #[test]
+    fn test_verify_mul() {
+        assert!(mock_accepts(|ctx, fpchip| {
+            let (a, b, c_s) = mul_example(ctx, fpchip, false);
+            let init_rand = mock_init_rand(ctx);
+            ZkMatrix::verify_mul(ctx, fpchip, &a, &b, &c_s, &init_rand);
+        }));
+    }
+
+    #[test]
+    fn test_verify_mul_forged_product() {
+        assert!(!mock_accepts(|ctx, fpchip| {
+            let (a, b, c_s) = mul_example(ctx, fpchip, true);
+            let init_rand = mock_init_rand(ctx);
+            ZkMatrix::verify_mul(ctx, fpchip, &a, &b, &c_s, &init_rand);
+        }));
+    }
+
+    #[test]
+    fn test_verify_mul_amplified() {
+        assert!(mock_accepts(|ctx, fpchip| {
+            let (a, b, c_s) = mul_example(ctx, fpchip, false);
+            let init_rand = mock_init_rand(ctx);
+            ZkMatrix::verify_mul_amplified(ctx, fpchip, &a, &b, &c_s, &init_rand, 3);
+        }));
+        // the challenges squeezed out of init_rand are checked too
+        assert!(!mock_accepts(|ctx, fpchip| {
+            let (a, b, c_s) = mul_example(ctx, fpchip, true);
+            let init_rand = mock_init_rand(ctx);
+            ZkMatrix::verify_mul_amplified(ctx, fpchip, &a, &b, &c_s, &init_rand, 3);
+        }));
+    }
+
+    #[test]
+    fn test_verify_mul_two_sided() {
+        assert!(mock_accepts(|ctx, fpchip| {
+            let (a, b, c_s) = mul_example(ctx, fpchip, false);
+            let init_rand = mock_init_rand(ctx);
+            ZkMatrix::verify_mul_two_sided(ctx, fpchip, &a, &b, &c_s, &init_rand, 2);
+        }));
+        assert!(!mock_accepts(|ctx, fpchip| {
+            let (a, b, c_s) = mul_example(ctx, fpchip, true);
+            let init_rand = mock_init_rand(ctx);
+            ZkMatrix::verify_mul_two_sided(ctx, fpchip, &a, &b, &c_s, &init_rand, 2);
+        }));
+    }
+
+    #[test]
+    fn test_verify_mul_with_mode_two_sided() {
+        for forge in [false, true] {
+            let accepted = mock_accepts(|ctx, fpchip| {
+                let (a, b, c_s) = mul_example(ctx, fpchip, forge);
+                let init_rand = mock_init_rand(ctx);
+                let mode = FreivaldsMode::TwoSided;
+                ZkMatrix::verify_mul_with_mode(ctx, fpchip, &a, &b, &c_s, &init_rand, mode, 1);
+            });
+            assert_eq!(accepted, !forge);
+        }
+    }
+
+    /// Checks the unscaled products of the pairs of matrices in `pairs` together with `verify_mul_batch`;
+    /// if `forge` is `Some(i)`, the product of the `i`-th pair is forged
+    fn batch_accepts(pairs: Vec<(Vec<Vec<f64>>, Vec<Vec<f64>>)>, forge: Option<usize>) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let mut triples = Vec::new();
+            for (i, (a, b)) in pairs.iter().enumerate() {
+                let a = ZkMatrix::new(ctx, fpchip, a);
+                let b = ZkMatrix::new(ctx, fpchip, b);
+                let c_s = honest_prover_mat_mul(ctx, &a.matrix, &b.matrix);
+                let c_s = if forge == Some(i) { forge_last_entry(ctx, &c_s) } else { c_s };
+                triples.push((a, b, c_s));
+            }
+            let products: Vec<_> = triples.iter().map(|(a, b, c_s)| (a, b, c_s)).collect();
+            let init_rand = mock_init_rand(ctx);
+            ZkMatrix::verify_mul_batch(ctx, fpchip, &products, &init_rand, 2);
+        });
+    }
+
+    /// Three pairs of `2 X 3` and `3 X 2` matrices
+    fn uniform_pairs() -> Vec<(Vec<Vec<f64>>, Vec<Vec<f64>>)> {
+        let a = vec![vec![1.0, 2.0, -0.5], vec![3.0, 4.0, 0.25]];
+        let b = vec![vec![1.0, 0.5], vec![-1.0, 2.0], vec![0.75, -3.0]];
+        let c = vec![vec![0.5, -1.0, 2.0], vec![1.5, 0.0, -0.25]];
+        let d = vec![vec![2.0, 0.0], vec![0.0, -1.5], vec![1.0, 1.0]];
+        return vec![(a, b.clone()), (c.clone(), b), (c, d)];
+    }
+
+    /// Pairs with the products of dimensions `2 X 2`, `3 X 3` and `1 X 1`
+    fn mixed_pairs() -> Vec<(Vec<Vec<f64>>, Vec<Vec<f64>>)> {
+        let a = vec![vec![1.0, 2.0, -0.5], vec![3.0, 4.0, 0.25]];
+        let b = vec![vec![1.0, 0.5], vec![-1.0, 2.0], vec![0.75, -3.0]];
+        let row = vec![vec![0.5, -1.0, 2.0, 1.5]];
+        let col = vec![vec![2.0], vec![0.25], vec![-1.0], vec![3.0]];
+        return vec![(a.clone(), b.clone()), (b, a), (row, col)];
+    }
+
+    #[test]
+    fn test_verify_mul_batch() {
+        assert!(batch_accepts(uniform_pairs(), None));
+        assert!(batch_accepts(mixed_pairs(), None));
+    }
+
+    #[test]
+    fn test_verify_mul_batch_forged_first() {
+        assert!(!batch_accepts(uniform_pairs(), Some(0)));
+        assert!(!batch_accepts(mixed_pairs(), Some(0)));
+    }
+
+    #[test]
+    fn test_verify_mul_batch_forged_last() {
+        assert!(!batch_accepts(uniform_pairs(), Some(2)));
+        assert!(!batch_accepts(mixed_pairs(), Some(2)));
+    }
+
+    #[test]
+    fn test_freivalds_soundness_cap() {
+        let max_bits = freivalds_max_soundness_bits::<Fr>();
+        assert_eq!(freivalds_soundness_bits::<Fr>(3, 100), max_bits as f64);
+        assert!(freivalds_soundness_bits::<Fr>(3, 1) < max_bits as f64);
+        assert_eq!(freivalds_num_reps::<Fr>(max_bits, 3), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_freivalds_num_reps_above_cap() {
+        freivalds_num_reps::<Fr>(300, 3);
+    }
+
+    #[test]
+    fn test_try_new() {
+        let mut builder = GateThreadBuilder::<Fr>::new(false);
+        let ctx = builder.main(0);
+        let fpchip = FixedPointChip::<Fr, PRECISION_BITS>::default(12);
+
+        let ragged = vec![vec![1.0, 2.0], vec![3.0]];
+        assert_eq!(
+            ZkMatrix::try_new(ctx, &fpchip, &ragged).err(),
+            Some(MatrixError::RaggedRows { operand: "matrix", row: 1, expected: 2, found: 1 })
+        );
+        let empty: Vec<Vec<f64>> = vec![];
+        assert_eq!(
+            ZkMatrix::try_new(ctx, &fpchip, &empty).err(),
+            Some(MatrixError::Empty { operand: "matrix" })
+        );
+    }
+
+    #[test]
+    fn test_try_verify_mul_errors() {
+        let mut builder = GateThreadBuilder::<Fr>::new(false);
+        let ctx = builder.main(0);
+        let fpchip = FixedPointChip::<Fr, PRECISION_BITS>::default(12);
+
+        let a = ZkMatrix::new(ctx, &fpchip, &vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+        let b = ZkMatrix::new(ctx, &fpchip, &vec![vec![1.0], vec![0.5]]);
+        let c_s = honest_prover_mat_mul(ctx, &a.matrix, &b.matrix);
+        let init_rand = ctx.load_witness(Fr::from(1234));
+
+        // zero repetitions
+        assert_eq!(
+            ZkMatrix::try_verify_mul_batch(ctx, &fpchip, &vec![(&a, &b, &c_s)], &init_rand, 0),
+            Err(MatrixError::ZeroRepetitions { op: "verify_mul_batch" })
+        );
+        assert_eq!(
+            ZkMatrix::try_verify_mul_with_mode(
+                ctx,
+                &fpchip,
+                &a,
+                &b,
+                &c_s,
+                &init_rand,
+                FreivaldsMode::TwoSided,
+                0
+            ),
+            Err(MatrixError::ZeroRepetitions { op: "verify_mul_with_mode" })
+        );
+
+        // no products
+        assert_eq!(
+            ZkMatrix::try_verify_mul_batch(ctx, &fpchip, &vec![], &init_rand, 1),
+            Err(MatrixError::Empty { operand: "products" })
+        );
+
+        // mismatched product
+        let a_times_a = honest_prover_mat_mul(ctx, &a.matrix, &a.matrix);
+        assert_eq!(
+            ZkMatrix::try_verify_mul_batch(
+                ctx,
+                &fpchip,
+                &vec![(&a, &b, &a_times_a)],
+                &init_rand,
+                1
+            ),
+            Err(MatrixError::ShapeMismatch {
+                op: "verify_mul_batch",
+                lhs: "c_s",
+                lhs_dims: (2, 2),
+                rhs: "product",
+                rhs_dims: (2, 1)
+            })
+        );
+        assert_eq!(
+            ZkMatrix::try_verify_mul(ctx, &fpchip, &b, &a, &c_s, &init_rand),
+            Err(MatrixError::ShapeMismatch {
+                op: "verify_mul",
+                lhs: "a",
+                lhs_dims: (2, 1),
+                rhs: "b",
+                rhs_dims: (2, 2)
+            })
+        );
+
+        // ragged product
+        let mut ragged = a_times_a.clone();
+        ragged[1].pop();
+        assert_eq!(
+            ZkMatrix::try_verify_mul(ctx, &fpchip, &a, &a, &ragged, &init_rand),
+            Err(MatrixError::RaggedRows { operand: "c_s", row: 1, expected: 2, found: 1 })
+        );
+
+        // dimensions which do not match the entries
+        let mut wrong_dims = a.clone();
+        wrong_dims.num_rows = 3;
+        assert_eq!(
+            ZkMatrix::try_verify_mul(ctx, &fpchip, &wrong_dims, &b, &c_s, &init_rand),
+            Err(MatrixError::InconsistentDims { operand: "a", stored: (3, 2), actual: (2, 2) })
+        );
+    }
+
+    #[test]
+    fn test_try_field_ops_errors() {
+        let mut builder = GateThreadBuilder::<Fr>::new(false);
+        let ctx = builder.main(0);
+        let fpchip = FixedPointChip::<Fr, PRECISION_BITS>::default(12);
+
+        let a = ZkMatrix::new(ctx, &fpchip, &vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+        assert!(try_honest_prover_mat_mul(ctx, &a.matrix, &a.matrix).is_err());
+        let v = ZkVector::new(ctx, &fpchip, &vec![1.0, 2.0]);
+        assert!(try_field_mat_vec_mul(ctx, fpchip.gate(), &a.matrix, &v.v).is_err());
+        let long_v = ZkVector::new(ctx, &fpchip, &vec![1.0, 2.0, 3.0, 4.0]);
+        assert!(try_mat_times_diag_mat(ctx, fpchip.gate(), &a.matrix, &long_v.v).is_err());
+        let b = ZkMatrix::new(ctx, &fpchip, &vec![vec![1.0, 2.0], vec![4.0, 5.0]]);
+        let tol = BigUint::from(1u32);
+        assert!(try_check_mat_diff(ctx, fpchip.range_gate(), &a.matrix, &b.matrix, &tol).is_err());
+    }
+
+    /// Checks the quotient of `verified_div` for `num/den`, shifted by `shift` units of `2^-PRECISION_BITS`, with `check_div_quotient`
+    fn div_accepts(num: f64, den: f64, shift: i64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let num = ctx.load_witness(fpchip.quantization(num));
+            let den = ctx.load_witness(fpchip.quantization(den));
+            let q = verified_div(ctx, fpchip, num, den, 4);
+            let shift_fe = Fr::from(shift.unsigned_abs());
+            let shift_fe = if shift < 0 { -shift_fe } else { shift_fe };
+            let q_shifted = ctx.load_witness(*q.value() + shift_fe);
+            check_div_quotient(ctx, fpchip, num, den, q_shifted, 4);
+        });
+    }
+
+    #[test]
+    fn test_verified_div() {
+        assert!(div_accepts(1.0, 3.0, 0));
+        assert!(div_accepts(-1.0, 3.0, 0));
+        assert!(div_accepts(2.5, 0.5, 0));
+        assert!(mock_accepts(|ctx, fpchip| {
+            let num = ctx.load_witness(fpchip.quantization(1.0));
+            let den = ctx.load_witness(fpchip.quantization(4.0));
+            let q = verified_div(ctx, fpchip, num, den, 4);
+            fpchip.gate().assert_is_const(ctx, &q, &fpchip.quantization(0.25));
+        }));
+    }
+
+    #[test]
+    fn test_verified_div_off_by_one() {
+        for (num, den) in [(1.0, 3.0), (-1.0, 3.0), (1.0, 4.0)] {
+            assert!(!div_accepts(num, den, 1));
+            assert!(!div_accepts(num, den, -1));
+        }
+    }
+
+    #[test]
+    fn test_verified_div_zero_den() {
+        assert!(!mock_accepts(|ctx, fpchip| {
+            let num = ctx.load_witness(fpchip.quantization(1.0));
+            let den = ctx.load_zero();
+            verified_div(ctx, fpchip, num, den, 4);
+        }));
+    }
+
+    /// Checks the root of `verified_sqrt` for `x`, shifted by `shift` units of `2^-PRECISION_BITS`, with `check_sqrt_root`
+    fn sqrt_accepts(x: f64, shift: i64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let x = ctx.load_witness(fpchip.quantization(x));
+            let s = verified_sqrt(ctx, fpchip, x, 36);
+            let shift_fe = Fr::from(shift.unsigned_abs());
+            let shift_fe = if shift < 0 { -shift_fe } else { shift_fe };
+            let s_shifted = ctx.load_witness(*s.value() + shift_fe);
+            check_sqrt_root(ctx, fpchip, x, s_shifted, 36);
+        });
+    }
+
+    #[test]
+    fn test_verified_sqrt() {
+        assert!(sqrt_accepts(2.0, 0));
+        assert!(sqrt_accepts(0.0, 0));
+        assert!(mock_accepts(|ctx, fpchip| {
+            let x = ctx.load_witness(fpchip.quantization(6.25));
+            let s = verified_sqrt(ctx, fpchip, x, 36);
+            fpchip.gate().assert_is_const(ctx, &s, &fpchip.quantization(2.5));
+        }));
+    }
+
+    #[test]
+    fn test_verified_sqrt_wrong_root() {
+        for x in [2.0, 6.25] {
+            assert!(!sqrt_accepts(x, 1));
+            assert!(!sqrt_accepts(x, -1));
+        }
+    }
+
+    #[test]
+    fn test_verified_sqrt_negative() {
+        assert!(!mock_accepts(|ctx, fpchip| {
+            let x = ctx.load_witness(fpchip.quantization(-4.0));
+            verified_sqrt(ctx, fpchip, x, 36);
+        }));
+    }
+
+    /// Field element of the signed integer `x`, with negative numbers represented by their field negatives
+    fn signed_fe(x: i64) -> Fr {
+        let x_fe = Fr::from(x.unsigned_abs());
+        return if x < 0 { -x_fe } else { x_fe };
+    }
+
+    /// Checks that `is_negative` and `signed_abs` of the field element of `x` are `neg` and `abs`
+    fn sign_abs_accepts(x: i64, max_bits: usize, neg: bool, abs: i64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let range = fpchip.range_gate();
+            let x = ctx.load_witness(signed_fe(x));
+            let is_neg = is_negative(ctx, range, x, max_bits);
+            fpchip.gate().assert_is_const(ctx, &is_neg, &Fr::from(neg as u64));
+            let x_abs = signed_abs(ctx, range, x, max_bits);
+            fpchip.gate().assert_is_const(ctx, &x_abs, &signed_fe(abs));
+        });
+    }
+
+    #[test]
+    fn test_is_negative_signed_abs() {
+        let edge = (1i64 << 40) - 1;
+        for x in [-edge, -5, -1, 0, 1, 5, edge] {
+            assert!(sign_abs_accepts(x, 40, x < 0, x.abs()));
+        }
+    }
+
+    #[test]
+    fn test_is_negative_signed_abs_wrong_claim() {
+        let edge = (1i64 << 40) - 1;
+        for x in [-edge, -1, 1, edge] {
+            assert!(!sign_abs_accepts(x, 40, x >= 0, x.abs()));
+            assert!(!sign_abs_accepts(x, 40, x < 0, -x.abs()));
+        }
+        // zero is not negative
+        assert!(!sign_abs_accepts(0, 40, true, 0));
+    }
+
+    #[test]
+    fn test_is_negative_out_of_range() {
+        // |x| < 2^max_bits is constrained, so -2^max_bits and 2^max_bits are rejected
+        for x in [-(1i64 << 40), 1i64 << 40] {
+            assert!(!mock_accepts(|ctx, fpchip| {
+                let x = ctx.load_witness(signed_fe(x));
+                is_negative(ctx, fpchip.range_gate(), x, 40);
+            }));
+            assert!(!mock_accepts(|ctx, fpchip| {
+                let x = ctx.load_witness(signed_fe(x));
+                signed_abs(ctx, fpchip.range_gate(), x, 40);
+            }));
+        }
+    }
+
+    /// Checks that `max_of_nonneg` of `xs` is `max`
+    fn max_accepts(xs: Vec<i64>, max: i64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let xs: Vec<AssignedValue<Fr>> =
+                xs.iter().map(|x| ctx.load_witness(signed_fe(*x))).collect();
+            let out = max_of_nonneg(ctx, fpchip.range_gate(), &xs, 40);
+            fpchip.gate().assert_is_const(ctx, &out, &signed_fe(max));
+        });
+    }
+
+    #[test]
+    fn test_max_of_nonneg() {
+        let edge = (1i64 << 40) - 1;
+        assert!(max_accepts(vec![3, 0, edge, 7], edge));
+        assert!(max_accepts(vec![0, 0], 0));
+        assert!(max_accepts(vec![7, 3], 7));
+        assert!(!max_accepts(vec![3, 0, edge, 7], 7));
+        assert!(!max_accepts(vec![0, 0], 1));
+    }
+
+    /// Square matrix with negative, zero and positive entries, all of which are exact in fixed point
+    fn norm_example() -> Vec<Vec<f64>> {
+        return vec![vec![1.0, -2.0, 0.0], vec![-3.5, 0.5, 4.0], vec![0.0, -1.0, -0.25]];
+    }
+
+    /// Checks that `norm` of the loaded `a` is `expected`
+    fn norm_accepts(
+        a: Vec<Vec<f64>>,
+        norm: impl FnOnce(
+            &mut Context<Fr>,
+            &FixedPointChip<Fr, PRECISION_BITS>,
+            &ZkMatrix<Fr, PRECISION_BITS>,
+        ) -> AssignedValue<Fr>,
+        expected: f64,
+    ) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &a);
+            let out = norm(ctx, fpchip, &a);
+            fpchip.gate().assert_is_const(ctx, &out, &fpchip.quantization(expected));
+        });
+    }
+
+    #[test]
+    fn test_trace_and_norms() {
+        let a = norm_example();
+        assert!(norm_accepts(a.clone(), |ctx, fpchip, a| a.trace(ctx, fpchip), 1.25));
+        assert!(norm_accepts(
+            a.clone(),
+            |ctx, fpchip, a| a.frobenius_norm_square(ctx, fpchip),
+            34.5625
+        ));
+        assert!(norm_accepts(a.clone(), |ctx, fpchip, a| a.max_abs_norm(ctx, fpchip, 36), 4.0));
+        // column sums of absolute values are 4.5, 3.5, 4.25 and row sums are 3, 8, 1.25
+        assert!(norm_accepts(a.clone(), |ctx, fpchip, a| a.one_norm(ctx, fpchip, 36), 4.5));
+        assert!(norm_accepts(a, |ctx, fpchip, a| a.inf_norm(ctx, fpchip, 36), 8.0));
+    }
+
+    #[test]
+    fn test_trace_and_norms_wrong_claim() {
+        let a = norm_example();
+        assert!(!norm_accepts(a.clone(), |ctx, fpchip, a| a.trace(ctx, fpchip), 1.5));
+        assert!(!norm_accepts(a.clone(), |ctx, fpchip, a| a.max_abs_norm(ctx, fpchip, 36), 3.5));
+        // the 1-norm and the infinity-norm are not interchangeable
+        assert!(!norm_accepts(a.clone(), |ctx, fpchip, a| a.one_norm(ctx, fpchip, 36), 8.0));
+        assert!(!norm_accepts(a, |ctx, fpchip, a| a.inf_norm(ctx, fpchip, 36), 4.5));
+    }
+
+    #[test]
+    fn test_norms_at_max_bits() {
+        // -(2^35 - 1) as a quantized value, i.e., the most negative entry allowed with max_bits = 35
+        let edge = 8.0 - 2.0_f64.powi(-32);
+        let a = vec![vec![-edge, 1.0], vec![0.0, -0.5]];
+        assert!(norm_accepts(a.clone(), |ctx, fpchip, a| a.max_abs_norm(ctx, fpchip, 35), edge));
+        assert!(norm_accepts(a.clone(), |ctx, fpchip, a| a.one_norm(ctx, fpchip, 35), edge));
+        assert!(norm_accepts(a, |ctx, fpchip, a| a.inf_norm(ctx, fpchip, 35), edge + 1.0));
+        // -2^35 as a quantized value is out of range
+        let a = vec![vec![-8.0, 1.0], vec![0.0, -0.5]];
+        assert!(!norm_accepts(a.clone(), |ctx, fpchip, a| a.max_abs_norm(ctx, fpchip, 35), 8.0));
+        assert!(!norm_accepts(a, |ctx, fpchip, a| a.inf_norm(ctx, fpchip, 35), 9.0));
+    }
+}
+
+/// Helpers for the `MockProver` tests of the gadgets in this crate
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use super::*;
+    use halo2_base::gates::builder::{GateThreadBuilder, RangeCircuitBuilder};
+    use halo2_base::halo2_proofs::dev::MockProver;
+    pub(crate) use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use std::env::set_var;
+    use std::sync::Mutex;
+
+    pub(crate) const PRECISION_BITS: u32 = 32;
+    const K: usize = 15;
+    const LOOKUP_BITS: usize = 12;
+
+    // the circuit configuration is passed to `RangeCircuitBuilder` through environment variables, so mock runs must not overlap
+    static MOCK_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Runs `f` in the first phase of a mock circuit and outputs whether `MockProver` accepts the resulting circuit
+    ///
+    /// Second phase functions are run in the same phase with the challenge from `mock_init_rand`; this exercises all the
+    /// constraints, but (unlike a real challenge) the challenge is known to the prover
+    pub(crate) fn mock_accepts(
+        f: impl FnOnce(&mut Context<Fr>, &FixedPointChip<Fr, PRECISION_BITS>),
+    ) -> bool {
+        let _guard = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_var("LOOKUP_BITS", LOOKUP_BITS.to_string());
+        let mut builder = GateThreadBuilder::new(false);
+        let fpchip = FixedPointChip::<Fr, PRECISION_BITS>::default(LOOKUP_BITS);
+        f(builder.main(0), &fpchip);
+        builder.config(K, Some(9));
+        let circuit = RangeCircuitBuilder::mock(builder);
+        return MockProver::run(K as u32, &circuit, vec![]).unwrap().verify().is_ok();
+    }
+
+    /// Loads the fixed challenge used in place of the second phase challenge
+    pub(crate) fn mock_init_rand(ctx: &mut Context<Fr>) -> AssignedValue<Fr> {
+        return ctx.load_witness(Fr::from(0x1234_5678_9abc_def0u64));
+    }
+
+    /// Loads a copy of the matrix `c_s` with its last entry shifted by one, as a malicious prover would commit to it
+    pub(crate) fn forge_last_entry(
+        ctx: &mut Context<Fr>,
+        c_s: &Vec<Vec<AssignedValue<Fr>>>,
+    ) -> Vec<Vec<AssignedValue<Fr>>> {
+        let i = c_s.len() - 1;
+        let j = c_s[0].len() - 1;
+        let mut forged: Vec<Vec<AssignedValue<Fr>>> = Vec::new();
+        for (k, row) in c_s.iter().enumerate() {
+            let mut new_row: Vec<AssignedValue<Fr>> = Vec::new();
+            for (l, elem) in row.iter().enumerate() {
+                let shift = Fr::from((k == i && l == j) as u64);
+                new_row.push(ctx.load_witness(*elem.value() + shift));
+            }
+            forged.push(new_row);
+        }
+        return forged;
+    }
+}
@@ -0,0 +1,247 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+use std::cmp;
+
+/// Activation functions supported by `dense_layer`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Activation {
+    /// `z`
+    Identity,
+    /// `max(z, 0)`
+    Relu,
+    /// `z` for `z >= 0` and `alpha*z` otherwise
+    LeakyRelu(f64),
+    /// Piecewise linear approximation of the sigmoid, `min(max(slope*z + 0.5, 0), 1)` with `0 < slope <= 1`;
+    /// e.g. `slope = 0.2` (Keras) or `slope = 1/6` (PyTorch)
+    HardSigmoid(f64),
+}
+
+/// Applies `activation` to the fixed point number `z` with zk constraints and outputs the result
+///
+/// The activations other than `Identity` constrain `|z| < 2^max_bits` (as a quantized value), which is needed for their sign
+/// checks (see `is_negative`); `Identity` adds no constraints and ignores `max_bits`
+///
+/// `Relu` uses one sign check, `LeakyRelu` one sign check and one rescaling, and `HardSigmoid` two sign checks and one rescaling
+pub fn apply_activation<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    z: AssignedValue<F>,
+    activation: Activation,
+    max_bits: usize,
+) -> AssignedValue<F> {
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    match activation {
+        Activation::Identity => {
+            return z;
+        }
+        Activation::Relu => {
+            let is_neg = is_negative(ctx, range, z, max_bits);
+            return gate.mul_not(ctx, is_neg, z);
+        }
+        Activation::LeakyRelu(alpha) => {
+            let is_neg = is_negative(ctx, range, z, max_bits);
+            let alpha_z_s = gate.mul(ctx, z, Constant(fpchip.quantization(alpha)));
+            let (alpha_z, _) = fpchip.signed_div_scale(ctx, alpha_z_s);
+            return gate.select(ctx, alpha_z, z, is_neg);
+        }
+        Activation::HardSigmoid(slope) => {
+            assert!(slope > 0.0 && slope <= 1.0);
+            // t = slope*z + 0.5
+            let t_s = gate.mul_add(
+                ctx,
+                z,
+                Constant(fpchip.quantization(slope)),
+                Constant(fpchip.quantization(0.5) * quant),
+            );
+            let (t, _) = fpchip.signed_div_scale(ctx, t_s);
+            // |t| < 2^t_bits as long as slope <= 1, and |1 - t| < 2^(t_bits + 1)
+            let t_bits = cmp::max(max_bits, PRECISION_BITS as usize) + 1;
+            let t_is_neg = is_negative(ctx, range, t, t_bits);
+            let t_clamped_below = gate.mul_not(ctx, t_is_neg, t);
+            let one_minus_t = gate.sub(ctx, Constant(quant), t);
+            let t_above_one = is_negative(ctx, range, one_minus_t, t_bits + 1);
+            return gate.select(ctx, Constant(quant), t_clamped_below, t_above_one);
+        }
+    }
+}
+
+/// Given a weight matrix `w` (`K X N` dimension), a batch of inputs `x_batch` (`N X B` dimension, one input per column) and a
+/// bias vector `bias` (`K` dimension) in fixed point representation with `fpchip`, computes the dense layer
+/// `activation(w*x_batch + bias)` (`K X B` dimension), where `bias` is added to every column
+///
+/// The unscaled product `w*x_batch` is committed with `honest_prover_mat_mul`, the bias is added at the doubled scale and each entry
+/// is rescaled once; unless `activation` is `Identity`, the pre-activations are constrained to satisfy `|z| < 2^max_bits`
+/// (as quantized values) (see `apply_activation`)
+///
+/// Outputs the layer output and the unscaled product; the product must be verified in the second phase with `verify_dense_layers`,
+/// and the output can be fed as `x_batch` to the next layer
+///
+/// Leads to about `K*B*(94 + cost of activation)` cells
+pub fn dense_layer<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    w: &ZkMatrix<F, PRECISION_BITS>,
+    x_batch: &ZkMatrix<F, PRECISION_BITS>,
+    bias: &ZkVector<F, PRECISION_BITS>,
+    activation: Activation,
+    max_bits: usize,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>) {
+    assert_eq!(w.num_col, x_batch.num_rows);
+    assert_eq!(w.num_rows, bias.size());
+
+    let gate: &GateChip<F> = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    let w_times_x = honest_prover_mat_mul(ctx, &w.matrix, &x_batch.matrix);
+
+    let mut out: Vec<Vec<AssignedValue<F>>> = Vec::new();
+    for (row, bias_i) in w_times_x.iter().zip(bias.v.iter()) {
+        let mut new_row: Vec<AssignedValue<F>> = Vec::new();
+        for elem in row {
+            let z_s = gate.mul_add(ctx, *bias_i, Constant(quant), *elem);
+            let (z, _) = fpchip.signed_div_scale(ctx, z_s);
+            new_row.push(apply_activation(ctx, fpchip, z, activation, max_bits));
+        }
+        out.push(new_row);
+    }
+
+    let out = ZkMatrix { matrix: out, num_rows: w.num_rows, num_col: x_batch.num_col };
+    return (out, w_times_x);
+}
+
+/// Second phase function for the dense layers; verifies the products committed by `dense_layer` for all the layers at once
+///
+/// Each entry of `layers` is `(w, x_batch, w_times_x)` for one layer, where `w` and `x_batch` are the inputs of `dense_layer` and
+/// `w_times_x` is its second output; all the layers share the same challenges (see `ZkMatrix::verify_mul_batch`)
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+/// including every committed product `w_times_x`; these are free witnesses until this check runs (and the inputs of the later layers
+/// are derived from them), so committing only to the weights and the input of the first layer is *not* enough. The challenge
+/// `rlc.gamma()` of the second phase commits to all the cells of the first phase and can be used directly
+///
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn verify_dense_layers<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    layers: &Vec<(
+        &ZkMatrix<F, PRECISION_BITS>,
+        &ZkMatrix<F, PRECISION_BITS>,
+        &Vec<Vec<AssignedValue<F>>>,
+    )>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, layers, &init_rand, num_reps);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+    use halo2_base::gates::builder::GateThreadBuilder;
+
+    /// Checks that `apply_activation` of `z` is `expected`
+    fn activation_accepts(z: f64, activation: Activation, expected: f64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let z = ctx.load_witness(fpchip.quantization(z));
+            let out = apply_activation(ctx, fpchip, z, activation, 36);
+            fpchip.gate().assert_is_const(ctx, &out, &fpchip.quantization(expected));
+        });
+    }
+
+    #[test]
+    fn test_relu() {
+        assert!(activation_accepts(-1.5, Activation::Relu, 0.0));
+        assert!(activation_accepts(0.0, Activation::Relu, 0.0));
+        assert!(activation_accepts(2.25, Activation::Relu, 2.25));
+        assert!(!activation_accepts(-1.5, Activation::Relu, -1.5));
+        assert!(!activation_accepts(2.25, Activation::Relu, 0.0));
+    }
+
+    #[test]
+    fn test_leaky_relu() {
+        assert!(activation_accepts(-2.0, Activation::LeakyRelu(0.25), -0.5));
+        assert!(activation_accepts(3.0, Activation::LeakyRelu(0.25), 3.0));
+        assert!(!activation_accepts(-2.0, Activation::LeakyRelu(0.25), -2.0));
+    }
+
+    #[test]
+    fn test_hard_sigmoid() {
+        let hard_sigmoid = Activation::HardSigmoid(0.25);
+        // 0.25*z + 0.5 reaches 0 at z = -2 and 1 at z = 2
+        assert!(activation_accepts(-3.0, hard_sigmoid, 0.0));
+        assert!(activation_accepts(-2.0, hard_sigmoid, 0.0));
+        assert!(activation_accepts(-1.75, hard_sigmoid, 0.0625));
+        assert!(activation_accepts(1.0, hard_sigmoid, 0.75));
+        assert!(activation_accepts(1.75, hard_sigmoid, 0.9375));
+        assert!(activation_accepts(2.0, hard_sigmoid, 1.0));
+        assert!(activation_accepts(3.0, hard_sigmoid, 1.0));
+        // the unclamped values are rejected
+        assert!(!activation_accepts(-3.0, hard_sigmoid, -0.25));
+        assert!(!activation_accepts(3.0, hard_sigmoid, 1.25));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hard_sigmoid_slope_above_one() {
+        let mut builder = GateThreadBuilder::<Fr>::new(false);
+        let ctx = builder.main(0);
+        let fpchip = FixedPointChip::<Fr, PRECISION_BITS>::default(12);
+        let z = ctx.load_witness(fpchip.quantization(1.0));
+        apply_activation(ctx, &fpchip, z, Activation::HardSigmoid(2.0), 36);
+    }
+
+    /// Runs a two-layer network (ReLU, then identity) on a batch of two inputs and checks the outputs `(3, 3.75)`; if `forge`
+    /// is set, the last entry of the committed product of that layer is forged before the second phase
+    fn two_layer_accepts(forge: Option<usize>) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let x_batch = ZkMatrix::new(ctx, fpchip, &vec![vec![1.0, 2.0], vec![3.0, -1.0]]);
+            let w1 = ZkMatrix::new(
+                ctx,
+                fpchip,
+                &vec![vec![1.0, -1.0], vec![0.5, 2.0], vec![-1.0, 0.25]],
+            );
+            let b1 = ZkVector::new(ctx, fpchip, &vec![0.5, -1.0, 0.0]);
+            let w2 = ZkMatrix::new(ctx, fpchip, &vec![vec![1.0, 0.5, -2.0]]);
+            let b2 = ZkVector::new(ctx, fpchip, &vec![0.25]);
+
+            // pre-activations are [[-1.5, 3.5], [5.5, -2], [-0.25, -2.25]]
+            let (h, w1_times_x) =
+                dense_layer(ctx, fpchip, &w1, &x_batch, &b1, Activation::Relu, 36);
+            let (out, w2_times_h) =
+                dense_layer(ctx, fpchip, &w2, &h, &b2, Activation::Identity, 36);
+            for (elem, expected) in out.matrix[0].iter().zip([3.0, 3.75]) {
+                fpchip.gate().assert_is_const(ctx, elem, &fpchip.quantization(expected));
+            }
+
+            let w1_times_x =
+                if forge == Some(0) { forge_last_entry(ctx, &w1_times_x) } else { w1_times_x };
+            let w2_times_h =
+                if forge == Some(1) { forge_last_entry(ctx, &w2_times_h) } else { w2_times_h };
+            let init_rand = mock_init_rand(ctx);
+            let layers = vec![(&w1, &x_batch, &w1_times_x), (&w2, &h, &w2_times_h)];
+            verify_dense_layers(ctx, fpchip, &layers, &init_rand, 2);
+        });
+    }
+
+    #[test]
+    fn test_two_layers() {
+        assert!(two_layer_accepts(None));
+    }
+
+    #[test]
+    fn test_two_layers_forged_product() {
+        assert!(!two_layer_accepts(Some(0)));
+        assert!(!two_layer_accepts(Some(1)));
+    }
+}
@@ -0,0 +1,225 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+use std::cmp;
+
+/// Given the vector of singular values `d` verified by `check_svd_phase0` and `check_svd_phase1`, outputs the vector of
+/// reciprocals `1/d[i]` for the singular values with `d[i] > cutoff`, and `0` for the others
+///
+/// The reciprocals are computed with `verified_div`; `max_bits_d` is the same as the one used in `check_svd_phase0`
+///
+/// `cutoff` is a (public) constant which should be at least `2^-PRECISION_BITS`; the reciprocals are then less than `1/cutoff`
+pub fn pseudo_reciprocals<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    cutoff: f64,
+    max_bits_d: u32,
+) -> ZkVector<F, PRECISION_BITS> {
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    let max_bits = (max_bits_d + PRECISION_BITS) as usize;
+    let cutoff_q = fpchip.quantization(cutoff);
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+    let one = ctx.load_constant(quant);
+
+    let mut recip: Vec<AssignedValue<F>> = Vec::new();
+    for elem in &d.v {
+        let above_cutoff = range.is_less_than(ctx, Constant(cutoff_q), *elem, max_bits);
+        // divide 1.0 by 1.0 for the singular values which are zeroed, so that the division is always well defined
+        let den = gate.select(ctx, *elem, Constant(quant), above_cutoff);
+        let elem_recip = verified_div(ctx, fpchip, one, den, max_bits + 1);
+        recip.push(gate.mul(ctx, elem_recip, above_cutoff));
+    }
+    return ZkVector { v: recip };
+}
+
+/// Given the outputs `u_t` (`N X N` dimension) and `v_t` (`M X M` dimension) of `check_svd_phase0` and the vector of singular
+/// values `d` (`min{N, M}` dimension) of a matrix `a`, performs the first part of the computation of the Moore-Penrose pseudo-inverse
+/// `a^+ = v^T*Diag(1/d)*u^T` (`M X N` dimension), where the singular values less than or equal to `cutoff` are zeroed
+/// (see `pseudo_reciprocals`)
+///
+/// `check_svd_phase0` and `check_svd_phase1` must be used to verify the SVD of `a`; `max_bits_d` is the same as the one used there
+///
+/// Outputs the reciprocals, the matrix `v^T*Diag(1/d)` (padded with zeroes to `M X N`, and rescaled), its unscaled product with `u^T`,
+/// and the pseudo-inverse, which is the rescaled product
+///
+/// Must call `check_pinv_phase1` function following this function in the second phase, which verifies the committed product;
+/// the pseudo-inverse is only constrained after that
+///
+/// Leads to about `94*M*(min{N, M} + N)` cells for the rescalings; e.g., the minimum norm least squares solution of `a.x = b`
+/// can then be computed as `b.mul(ctx, fpchip, &a_pinv)`
+pub fn check_pinv_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    cutoff: f64,
+    max_bits_d: u32,
+) -> (
+    ZkVector<F, PRECISION_BITS>,
+    ZkMatrix<F, PRECISION_BITS>,
+    Vec<Vec<AssignedValue<F>>>,
+    ZkMatrix<F, PRECISION_BITS>,
+) {
+    #![allow(non_snake_case)]
+    let N = u_t.num_rows;
+    let M = v_t.num_rows;
+    assert_eq!(u_t.num_rows, u_t.num_col);
+    assert_eq!(v_t.num_rows, v_t.num_col);
+    assert_eq!(d.size(), cmp::min(N, M));
+
+    let gate: &GateChip<F> = fpchip.gate();
+
+    let recip = pseudo_reciprocals(ctx, fpchip, d, cutoff, max_bits_d);
+
+    // v^T*Diag(1/d) is M X min{N, M}; pad with zeroes to make it M X N
+    let vt_times_recip_s = mat_times_diag_mat(ctx, gate, &v_t.matrix, &recip.v);
+    let mut vd = ZkMatrix::rescale_matrix(ctx, fpchip, &vt_times_recip_s);
+    if vd.num_col < N {
+        let zero = ctx.load_constant(F::zero());
+        for row in &mut vd.matrix {
+            for _ in M..N {
+                row.push(zero);
+            }
+        }
+        vd.num_col = N;
+    }
+
+    let vd_times_ut = honest_prover_mat_mul(ctx, &vd.matrix, &u_t.matrix);
+    let a_pinv = ZkMatrix::rescale_matrix(ctx, fpchip, &vd_times_ut);
+
+    return (recip, vd, vd_times_ut, a_pinv);
+}
+
+/// Second phase function for computing the pseudo-inverse;
+///
+/// `check_pinv_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `u_t` as used in `check_pinv_phase0` and other inputs correspond to the outputs of `check_pinv_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// The product is checked with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree `N + M - 2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_pinv_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    vd: &ZkMatrix<F, PRECISION_BITS>,
+    vd_times_ut: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(vd, u_t, vd_times_ut)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+    use crate::svd::{check_svd_phase0, check_svd_phase1, err_calc};
+
+    /// Returns `(m, u, v, d)` such that `m = u*Diag(d)*v` for a `3 X 2` matrix `m` of numerical rank one, whose second
+    /// singular value is zeroed by a cutoff of `1e-3`; the pseudo-inverse of `m` is then `[[0, 0, 0], [0.3, 0.4, 0]]`
+    fn rank_deficient_example() -> (Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<f64>) {
+        let u = vec![vec![0.6, 0.8, 0.0], vec![0.8, -0.6, 0.0], vec![0.0, 0.0, 1.0]];
+        let v = vec![vec![0.0, 1.0], vec![1.0, 0.0]];
+        let d = vec![2.0, 1e-6];
+        let mut m: Vec<Vec<f64>> = Vec::new();
+        for i in 0..3 {
+            let mut row: Vec<f64> = Vec::new();
+            for j in 0..2 {
+                row.push(u[i][0] * d[0] * v[0][j] + u[i][1] * d[1] * v[1][j]);
+            }
+            m.push(row);
+        }
+        return (m, u, v, d);
+    }
+
+    /// Verifies the SVD of `rank_deficient_example` and computes its pseudo-inverse; if `forge` is set, the last entry of the
+    /// committed product `vd_times_ut` is forged before the second phase
+    fn pinv_accepts(forge: bool) -> bool {
+        let (m, u, v, d) = rank_deficient_example();
+        return mock_accepts(|ctx, fpchip| {
+            let m = ZkMatrix::new(ctx, fpchip, &m);
+            let u = ZkMatrix::new(ctx, fpchip, &u);
+            let v = ZkMatrix::new(ctx, fpchip, &v);
+            let d = ZkVector::new(ctx, fpchip, &d);
+            let (err_svd, err_u) = err_calc(PRECISION_BITS, 3, 4.0, 0.0, 0.0);
+            let (u_t, v_t, m_times_vt, u_times_ut, v_times_vt) =
+                check_svd_phase0(ctx, fpchip, &m, &u, &v, &d, err_svd, err_u, 2);
+            let (_, vd, vd_times_ut, a_pinv) =
+                check_pinv_phase0(ctx, fpchip, &u_t, &v_t, &d, 1e-3, 2);
+            let vd_times_ut = if forge { forge_last_entry(ctx, &vd_times_ut) } else { vd_times_ut };
+
+            let expected = vec![vec![0.0, 0.0, 0.0], vec![0.3, 0.4, 0.0]];
+            for (row, expected_row) in a_pinv.dequantize(fpchip).iter().zip(expected.iter()) {
+                for (elem, expected_elem) in row.iter().zip(expected_row.iter()) {
+                    assert!((elem - expected_elem).abs() < 1e-8);
+                }
+            }
+
+            let init_rand = mock_init_rand(ctx);
+            check_svd_phase1(
+                ctx,
+                fpchip,
+                &m,
+                &u,
+                &v,
+                &u_t,
+                &v_t,
+                &m_times_vt,
+                &u_times_ut,
+                &v_times_vt,
+                &init_rand,
+            );
+            check_pinv_phase1(ctx, fpchip, &u_t, &vd, &vd_times_ut, &init_rand, 2);
+        });
+    }
+
+    /// Checks that `pseudo_reciprocals` of `d` with `cutoff` is `expected`
+    fn recip_accepts(d: Vec<f64>, cutoff: f64, expected: Vec<f64>) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let d = ZkVector::new(ctx, fpchip, &d);
+            let recip = pseudo_reciprocals(ctx, fpchip, &d, cutoff, 2);
+            for (elem, expected_elem) in recip.v.iter().zip(expected.iter()) {
+                fpchip.gate().assert_is_const(ctx, elem, &fpchip.quantization(*expected_elem));
+            }
+        });
+    }
+
+    #[test]
+    fn test_pinv_rank_deficient() {
+        assert!(pinv_accepts(false));
+    }
+
+    #[test]
+    fn test_pinv_forged_product() {
+        assert!(!pinv_accepts(true));
+    }
+
+    #[test]
+    fn test_pseudo_reciprocals() {
+        assert!(recip_accepts(vec![2.0, 0.25, 1e-6], 1e-3, vec![0.5, 4.0, 0.0]));
+        // only singular values strictly greater than the cutoff are inverted
+        assert!(recip_accepts(vec![2.0, 0.25], 0.25, vec![0.5, 0.0]));
+    }
+
+    #[test]
+    fn test_pseudo_reciprocals_forged() {
+        // off by one unit of 2^-PRECISION_BITS
+        assert!(!recip_accepts(vec![2.0, 1e-6], 1e-3, vec![0.5 + 2.0_f64.powi(-32), 0.0]));
+        // the reciprocal of a zeroed singular value
+        assert!(!recip_accepts(vec![2.0, 1e-6], 1e-3, vec![0.5, 1e6]));
+    }
+}
@@ -0,0 +1,310 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+
+/// Outputs the constant matrix `c*Id` (`n X n` dimension) in fixed point representation
+fn scaled_identity<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    n: usize,
+    c: f64,
+) -> ZkMatrix<F, PRECISION_BITS> {
+    let mut m: Vec<Vec<AssignedValue<F>>> = Vec::new();
+    for i in 0..n {
+        let mut new_row: Vec<AssignedValue<F>> = Vec::new();
+        for j in 0..n {
+            let elem = if i == j { c } else { 0.0 };
+            new_row.push(ctx.load_constant(fpchip.quantization(elem)));
+        }
+        m.push(new_row);
+    }
+    return ZkMatrix { matrix: m, num_rows: n, num_col: n };
+}
+
+/// Commits the unscaled product `a*b` with `honest_prover_mat_mul`, adds `c*Id` to it (skipped for `c = 0.0`) and rescales it
+///
+/// Constrains the entries of the result to satisfy `|x| < 2^max_bits`, i.e., `2^(max_bits + PRECISION_BITS)` as quantized values
+///
+/// Outputs the result and the step `(a, b, a*b)` to be verified in the second phase
+fn committed_mul_step<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    b: &ZkMatrix<F, PRECISION_BITS>,
+    c: f64,
+    max_bits: u32,
+) -> (
+    ZkMatrix<F, PRECISION_BITS>,
+    (ZkMatrix<F, PRECISION_BITS>, ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>),
+) {
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    let a_times_b = honest_prover_mat_mul(ctx, &a.matrix, &b.matrix);
+    let mut c_s = a_times_b.clone();
+    if c != 0.0 {
+        // c*Id has scale 2^(2*PRECISION_BITS) to match the unscaled product
+        let c_q = fpchip.quantization(c) * quant;
+        for i in 0..c_s.len() {
+            c_s[i][i] = gate.add(ctx, c_s[i][i], Constant(c_q));
+        }
+    }
+    let res = ZkMatrix::rescale_matrix(ctx, fpchip, &c_s);
+    let bnd = BigUint::from(2u32).pow(max_bits + PRECISION_BITS);
+    check_mat_entries_bounded(ctx, &range, &res.matrix, &bnd);
+
+    return (res, (a.clone(), b.clone(), a_times_b));
+}
+
+/// Given a square matrix `a` (`N X N` dimension) in fixed point representation with `fpchip`, computes `a^k` by repeated squaring
+///
+/// Each of the (at most `2*log2(k)`) products is committed with `honest_prover_mat_mul` and rescaled before it is used in the next
+/// product; every rescaled product is constrained to satisfy `|x| < 2^max_bits` in each entry, so that the proof fails if the powers
+/// grow too much instead of overflowing. For `|a[i][j]| < 2^max_bits` as well, `2*(max_bits + PRECISION_BITS) + log2(N)` should be
+/// less than `3*PRECISION_BITS` (see `ZkMatrix::rescale_matrix`)
+///
+/// Outputs `a^k` and the list of the products `(x, y, x*y)` (as unscaled products) which must be verified in the second phase with
+/// `check_mat_products_phase1`; for `k <= 1` the list is empty (and `a^0` is the constant identity)
+///
+/// NOTE: every rescaling rounds, so the output is only an approximation of `a^k`; its error grows with `k` and the norm of `a`.
+/// E.g., for a stochastic matrix `a` this proves the `k`-step transition probabilities of the Markov chain
+pub fn mat_power_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    k: u32,
+    max_bits: u32,
+) -> (
+    ZkMatrix<F, PRECISION_BITS>,
+    Vec<(ZkMatrix<F, PRECISION_BITS>, ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>)>,
+) {
+    #![allow(non_snake_case)]
+    let N = a.num_rows;
+    assert_eq!(a.num_rows, a.num_col);
+
+    let mut steps = Vec::new();
+    if k == 0 {
+        return (scaled_identity(ctx, fpchip, N, 1.0), steps);
+    }
+
+    // a^k = product of a^(2^i) over the set bits i of k
+    let mut res: Option<ZkMatrix<F, PRECISION_BITS>> = None;
+    let mut base = a.clone();
+    let mut rem = k;
+    loop {
+        if rem & 1 == 1 {
+            res = match res {
+                None => Some(base.clone()),
+                Some(prev) => {
+                    let (prod, step) = committed_mul_step(ctx, fpchip, &prev, &base, 0.0, max_bits);
+                    steps.push(step);
+                    Some(prod)
+                }
+            };
+        }
+        rem >>= 1;
+        if rem == 0 {
+            break;
+        }
+        let (square, step) = committed_mul_step(ctx, fpchip, &base, &base, 0.0, max_bits);
+        steps.push(step);
+        base = square;
+    }
+
+    return (res.unwrap(), steps);
+}
+
+/// Given a square matrix `a` (`N X N` dimension) in fixed point representation with `fpchip` and public coefficients `coeffs`,
+/// computes the matrix polynomial `p(a) = coeffs[0]*Id + coeffs[1]*a + ... + coeffs[d]*a^d`
+///
+/// Uses Horner's rule, `r = (...((coeffs[d]*a + coeffs[d-1]*Id)*a + coeffs[d-2]*Id)...)*a + coeffs[0]*Id`; the first step only
+/// multiplies by a constant, and each of the other `d-1` products is committed with `honest_prover_mat_mul`, rescaled and bounded
+/// as in `mat_power_phase0`, with the same condition on `max_bits`
+///
+/// Outputs `p(a)` and the list of the products `(x, y, x*y)` (as unscaled products) which must be verified in the second phase with
+/// `check_mat_products_phase1`; for `d <= 1` the list is empty
+///
+/// E.g., `coeffs[i] = 1/i!` gives the Taylor approximation of the matrix exponential `exp(a)` of degree `d`
+pub fn mat_poly_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    coeffs: &Vec<f64>,
+    max_bits: u32,
+) -> (
+    ZkMatrix<F, PRECISION_BITS>,
+    Vec<(ZkMatrix<F, PRECISION_BITS>, ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>)>,
+) {
+    #![allow(non_snake_case)]
+    let N = a.num_rows;
+    assert_eq!(a.num_rows, a.num_col);
+    assert!(coeffs.len() >= 1);
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+    let d = coeffs.len() - 1;
+
+    let mut steps = Vec::new();
+    if d == 0 {
+        return (scaled_identity(ctx, fpchip, N, coeffs[0]), steps);
+    }
+
+    // r = coeffs[d]*a + coeffs[d-1]*Id, at the scale 2^(2*PRECISION_BITS)
+    let coeff_q = fpchip.quantization(coeffs[d]);
+    let diag_q = fpchip.quantization(coeffs[d - 1]) * quant;
+    let mut r_s: Vec<Vec<AssignedValue<F>>> = Vec::new();
+    for i in 0..N {
+        let mut new_row: Vec<AssignedValue<F>> = Vec::new();
+        for j in 0..N {
+            let elem = if i == j {
+                gate.mul_add(ctx, a.matrix[i][j], Constant(coeff_q), Constant(diag_q))
+            } else {
+                gate.mul(ctx, a.matrix[i][j], Constant(coeff_q))
+            };
+            new_row.push(elem);
+        }
+        r_s.push(new_row);
+    }
+    let mut r = ZkMatrix::rescale_matrix(ctx, fpchip, &r_s);
+    let bnd = BigUint::from(2u32).pow(max_bits + PRECISION_BITS);
+    check_mat_entries_bounded(ctx, &range, &r.matrix, &bnd);
+
+    for i in (0..(d - 1)).rev() {
+        let (prod, step) = committed_mul_step(ctx, fpchip, &r, a, coeffs[i], max_bits);
+        steps.push(step);
+        r = prod;
+    }
+
+    return (r, steps);
+}
+
+/// Second phase function for `mat_power_phase0` and `mat_poly_phase0`; verifies all the committed products at once with
+/// `ZkMatrix::verify_mul_batch`
+///
+/// `steps` is the second output of `mat_power_phase0` or `mat_poly_phase0` (or the concatenation of several of them); nothing is
+/// checked if it is empty
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+/// in particular of all the unscaled products in `steps`: each of them is unconstrained until it is checked here and the later factors
+/// are rescalings of them, so a commitment to `a` alone would let the prover pick them after seeing the challenge
+/// (`rlc.gamma()` of the second phase commits to every cell of the first phase)
+///
+/// For `s` products of `N X N` matrices the polynomial identity has degree `(s+1)*N - 2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_mat_products_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    steps: &Vec<(
+        ZkMatrix<F, PRECISION_BITS>,
+        ZkMatrix<F, PRECISION_BITS>,
+        Vec<Vec<AssignedValue<F>>>,
+    )>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    if steps.len() == 0 {
+        return;
+    }
+    let products: Vec<_> = steps.iter().map(|(x, y, x_times_y)| (x, y, x_times_y)).collect();
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+
+    /// Checks that `mat_power_phase0` of `[[1, 1], [0, 1]]` is `[[1, k], [0, 1]]`; if `forge` is set, the last committed product
+    /// is forged in the second phase
+    fn power_accepts(k: u32, max_bits: u32, forge: bool) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &vec![vec![1.0, 1.0], vec![0.0, 1.0]]);
+            let (a_k, mut steps) = mat_power_phase0(ctx, fpchip, &a, k, max_bits);
+            let expected = vec![vec![1.0, k as f64], vec![0.0, 1.0]];
+            for i in 0..2 {
+                for j in 0..2 {
+                    let expected_q = fpchip.quantization(expected[i][j]);
+                    fpchip.gate().assert_is_const(ctx, &a_k.matrix[i][j], &expected_q);
+                }
+            }
+            if forge {
+                let last = steps.len() - 1;
+                steps[last].2 = forge_last_entry(ctx, &steps[last].2);
+            }
+            let init_rand = mock_init_rand(ctx);
+            check_mat_products_phase1(ctx, fpchip, &steps, &init_rand, 2);
+        });
+    }
+
+    #[test]
+    fn test_mat_power_honest() {
+        for k in [0, 1, 2, 5, 6] {
+            assert!(power_accepts(k, 3, false));
+        }
+    }
+
+    #[test]
+    fn test_mat_power_forged_product() {
+        assert!(!power_accepts(5, 3, true));
+    }
+
+    #[test]
+    fn test_mat_power_too_large() {
+        // a^4 has an entry equal to 4 = 2^max_bits
+        assert!(!power_accepts(5, 2, false));
+    }
+
+    /// Checks that `mat_poly_phase0` of `[[1, 1], [0, 1]]` with `coeffs` is `expected`; if `forge` is set, the last committed
+    /// product is forged in the second phase
+    fn poly_accepts(coeffs: Vec<f64>, expected: Vec<Vec<f64>>, forge: bool) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &vec![vec![1.0, 1.0], vec![0.0, 1.0]]);
+            let (p_a, mut steps) = mat_poly_phase0(ctx, fpchip, &a, &coeffs, 3);
+            for i in 0..2 {
+                for j in 0..2 {
+                    let expected_q = fpchip.quantization(expected[i][j]);
+                    fpchip.gate().assert_is_const(ctx, &p_a.matrix[i][j], &expected_q);
+                }
+            }
+            if forge {
+                let last = steps.len() - 1;
+                steps[last].2 = forge_last_entry(ctx, &steps[last].2);
+            }
+            let init_rand = mock_init_rand(ctx);
+            check_mat_products_phase1(ctx, fpchip, &steps, &init_rand, 2);
+        });
+    }
+
+    #[test]
+    fn test_mat_poly() {
+        // 1 + 2*a + a^2 = (Id + a)^2 = [[4, 4], [0, 4]]
+        let coeffs = vec![1.0, 2.0, 1.0];
+        assert!(poly_accepts(coeffs.clone(), vec![vec![4.0, 4.0], vec![0.0, 4.0]], false));
+        assert!(!poly_accepts(coeffs.clone(), vec![vec![4.0, 3.0], vec![0.0, 4.0]], false));
+        assert!(!poly_accepts(coeffs, vec![vec![4.0, 4.0], vec![0.0, 3.0]], false));
+    }
+
+    #[test]
+    fn test_mat_poly_degree_three() {
+        // a^k = [[1, k], [0, 1]], so p(a) = [[p(1), p'(1)], [0, p(1)]] = [[0.75, 0.75], [0, 0.75]]; the intermediate
+        // results of Horner's rule have negative entries
+        let coeffs = vec![1.0, -1.0, 0.5, 0.25];
+        assert!(poly_accepts(coeffs.clone(), vec![vec![0.75, 0.75], vec![0.0, 0.75]], false));
+        assert!(!poly_accepts(coeffs, vec![vec![0.75, 0.5], vec![0.0, 0.75]], false));
+    }
+
+    #[test]
+    fn test_mat_poly_forged_product() {
+        let coeffs = vec![1.0, -1.0, 0.5, 0.25];
+        assert!(!poly_accepts(coeffs, vec![vec![0.75, 0.75], vec![0.0, 0.75]], true));
+    }
+}
@@ -0,0 +1,226 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, RangeChip, RangeInstructions};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+use halo2_base::Context;
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+use std::cmp;
+
+/// Given matrices `m` (`N X M` dimension), `q` (`N X K` dimension) and `r` (`K X M` dimension) in fixed point
+/// representation with `fpchip`, performs the first part of checks that `m = q*r` is a QR decomposition of `m`
+///
+/// `q` has orthonormal columns and `r` is upper triangular; either `K = N` (full QR) or `K = min{N, M}` (thin QR, which for
+/// tall matrices avoids the `N X N` matrix `q`)
+///
+/// Also takes as input tolerance levels `err_qr` and `err_q`, which determine the error up to which `m = q*r` and
+/// `q^T*q = Id` are checked (see `qr_err_calc`)
+///
+/// `max_bits_r` bounds the entries of `r`: they are constrained to satisfy `|r[i][j]| < 2^max_bits_r`; it is used to make sure
+/// no overflows occur while multiplying
+///
+/// If `nonneg_diag` is set, the diagonal entries of `r` are also constrained to be non-negative, which makes the decomposition unique
+/// for full rank `m`
+///
+/// Must call `check_qr_phase1` function following this function in the second phase to complete the QR check
+///
+/// The outputs are simply witnesses to be used for the corresponding variables in `check_qr_phase1`
+///
+/// NOTE: as for `check_svd_phase0`, the fixed point chip does not check for overflows, so one needs to place some bound on
+/// $\Vert m \Vert_2$, which is assumed to be enforced by the function or program calling this library
+pub fn check_qr_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    q: &ZkMatrix<F, PRECISION_BITS>,
+    r: &ZkMatrix<F, PRECISION_BITS>,
+    err_qr: f64,
+    err_q: f64,
+    max_bits_r: u32,
+    nonneg_diag: bool,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>, Vec<Vec<AssignedValue<F>>>) {
+    #![allow(non_snake_case)]
+    let N = m.num_rows;
+    let M = m.num_col;
+    let K = q.num_col;
+    assert_eq!(q.num_rows, N);
+    assert_eq!(r.num_rows, K);
+    assert_eq!(r.num_col, M);
+    assert!(K == N || K == cmp::min(N, M));
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    // check that the entries of q correspond to real numbers in the interval (-1.0,1.0) upto an error of 2^-PRECISION_BITS
+    let unit_bnd_q = BigUint::from(2u64.pow(PRECISION_BITS) + 1);
+    check_mat_entries_bounded(ctx, &range, &q.matrix, &unit_bnd_q);
+
+    // check that the entries of r correspond to real numbers in (-2^max_bits_r, 2^max_bits_r)
+    let r_bnd_q = BigUint::from(2u32).pow(max_bits_r + PRECISION_BITS);
+    check_mat_entries_bounded(ctx, &range, &r.matrix, &r_bnd_q);
+
+    check_upper_triangular(ctx, gate, &r.matrix);
+    if nonneg_diag {
+        for i in 0..cmp::min(K, M) {
+            range.range_check(ctx, r.matrix[i][i], (max_bits_r + PRECISION_BITS) as usize);
+        }
+    }
+
+    let q_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&q);
+
+    // define the doubly scaled errors
+    let err_qr_scale =
+        BigUint::from((err_qr * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+    let err_q_scale =
+        BigUint::from((err_q * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+    let quant_square = ctx.load_constant(quant * quant);
+
+    // q*r is doubly scaled, so m is scaled once more before comparing
+    let q_times_r = honest_prover_mat_mul(ctx, &q.matrix, &r.matrix);
+    let m_scaled = field_mat_scalar_mul(ctx, gate, &m.matrix, quant);
+    check_mat_diff(ctx, &range, &q_times_r, &m_scaled, &err_qr_scale);
+
+    // orthonormal columns
+    let qt_times_q = honest_prover_mat_mul(ctx, &q_t.matrix, &q.matrix);
+    check_mat_id(ctx, &range, &qt_times_q, &quant_square, &err_q_scale);
+
+    return (q_t, q_times_r, qt_times_q);
+}
+
+/// Second phase function for checking QR decomposition;
+///
+/// `check_qr_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `q`, `r` as used in `check_qr_phase0` and other inputs correspond to the outputs of `check_qr_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// Both products are checked together with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree
+/// `max{M, K} + N + K - 2`; `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_qr_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    q: &ZkMatrix<F, PRECISION_BITS>,
+    r: &ZkMatrix<F, PRECISION_BITS>,
+    q_t: &ZkMatrix<F, PRECISION_BITS>,
+    q_times_r: &Vec<Vec<AssignedValue<F>>>,
+    qt_times_q: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(q, r, q_times_r), (q_t, q, qt_times_q)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Calculates `err_qr` and `err_q` for `check_qr_phase0` from `eps_qr` and `eps_q`
+///
+/// `p` is the PRECISION_BITS for the fixed point chip
+///
+/// `size` is the size of the matrices for which the outputs are to be used
+///
+/// `max_norm` is the maximum operator norm of `m` (and hence of `r`) for which the outputs are to be used
+///
+/// `eps_qr` is the entrywise error of `m - q*r` and `eps_q` is the entrywise error of `q^T*q - Id`
+/// for the (floating point) decomposition before quantization
+///
+/// Quantizing `q` and `r` changes each entry of `q*r` by at most `size*(1 + max_norm)*2^-(p+1)` and quantizing `m` changes its
+/// entries by at most `2^-(p+1)`, up to second order terms; `err_q` is the same as `err_u` in `err_calc`
+pub fn qr_err_calc(p: u32, size: usize, max_norm: f64, eps_qr: f64, eps_q: f64) -> (f64, f64) {
+    let precision = 2.0_f64.powf(-1.0 * (p as f64 + 1.0));
+    let err_qr = precision * (size as f64) * (1.0 + max_norm + precision) + precision + eps_qr;
+    let err_q = eps_q + precision * (size as f64) * (2.0 * (1.0 + eps_q) + precision);
+    return (err_qr, err_q);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+
+    /// Checks `q*r` as a QR decomposition of `m`, whose operator norm is less than 7; if `forge` is set, the committed `q*r` is
+    /// forged in the second phase
+    fn qr_accepts(m: Vec<Vec<f64>>, q: Vec<Vec<f64>>, r: Vec<Vec<f64>>, forge: bool) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let size = cmp::max(m.len(), m[0].len());
+            let m = ZkMatrix::new(ctx, fpchip, &m);
+            let q = ZkMatrix::new(ctx, fpchip, &q);
+            let r = ZkMatrix::new(ctx, fpchip, &r);
+            let (err_qr, err_q) = qr_err_calc(PRECISION_BITS, size, 7.0, 0.0, 0.0);
+            let (q_t, q_times_r, qt_times_q) =
+                check_qr_phase0(ctx, fpchip, &m, &q, &r, err_qr, err_q, 4, true);
+            let q_times_r = if forge { forge_last_entry(ctx, &q_times_r) } else { q_times_r };
+            let init_rand = mock_init_rand(ctx);
+            check_qr_phase1(ctx, fpchip, &q, &r, &q_t, &q_times_r, &qt_times_q, &init_rand, 2);
+        });
+    }
+
+    fn square_m() -> Vec<Vec<f64>> {
+        return vec![vec![3.0, 0.0], vec![4.0, 5.0]];
+    }
+
+    /// The tall matrix `[[2, 0], [2, 3], [1, 3]]`, with the orthogonal matrix `q` (`3 X 3`) of its full QR decomposition;
+    /// its first two columns form the `q` of the thin QR decomposition
+    fn tall_m_and_q() -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let m = vec![vec![2.0, 0.0], vec![2.0, 3.0], vec![1.0, 3.0]];
+        let q = vec![
+            vec![2.0 / 3.0, -2.0 / 3.0, 1.0 / 3.0],
+            vec![2.0 / 3.0, 1.0 / 3.0, -2.0 / 3.0],
+            vec![1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0],
+        ];
+        return (m, q);
+    }
+
+    #[test]
+    fn test_qr_honest() {
+        let q = vec![vec![0.6, -0.8], vec![0.8, 0.6]];
+        let r = vec![vec![5.0, 4.0], vec![0.0, 3.0]];
+        assert!(qr_accepts(square_m(), q, r, false));
+    }
+
+    #[test]
+    fn test_qr_not_orthonormal() {
+        // q*r = m and r is upper triangular, but the columns of q are not orthonormal
+        let q = vec![vec![0.6, 0.0], vec![0.8, 1.0]];
+        let r = vec![vec![5.0, 0.0], vec![0.0, 5.0]];
+        assert!(!qr_accepts(square_m(), q, r, false));
+    }
+
+    #[test]
+    fn test_qr_not_upper_triangular() {
+        let q = vec![vec![0.6, -0.8], vec![0.8, 0.6]];
+        let r = vec![vec![5.0, 4.0], vec![0.5, 3.0]];
+        assert!(!qr_accepts(square_m(), q, r, false));
+    }
+
+    #[test]
+    fn test_qr_forged_product() {
+        let q = vec![vec![0.6, -0.8], vec![0.8, 0.6]];
+        let r = vec![vec![5.0, 4.0], vec![0.0, 3.0]];
+        assert!(!qr_accepts(square_m(), q, r, true));
+    }
+
+    #[test]
+    fn test_qr_thin() {
+        // q is 3 X 2, so q^T*q is the 2 X 2 identity and r is 2 X 2
+        let (m, q) = tall_m_and_q();
+        let q_thin: Vec<Vec<f64>> = q.iter().map(|row| row[..2].to_vec()).collect();
+        let r = vec![vec![3.0, 3.0], vec![0.0, 3.0]];
+        assert!(qr_accepts(m.clone(), q_thin.clone(), r.clone(), false));
+        assert!(!qr_accepts(m, q_thin, r, true));
+    }
+
+    #[test]
+    fn test_qr_full_tall() {
+        // q is 3 X 3 and r is 3 X 2 with a zero last row
+        let (m, q) = tall_m_and_q();
+        let r = vec![vec![3.0, 3.0], vec![0.0, 3.0], vec![0.0, 0.0]];
+        assert!(qr_accepts(m.clone(), q.clone(), r.clone(), false));
+        assert!(!qr_accepts(m, q, r, true));
+    }
+}
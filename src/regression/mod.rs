@@ -0,0 +1,522 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+use super::solve::*;
+use std::cmp;
+
+/// With zk constraints calculates the coefficient of determination `R^2 = 1 - |y - x.beta|^2/|y - mean(y)|^2` of the linear
+/// model `beta` for the design matrix `x` (`n X p` dimension) and the response `y` (`n` dimension)
+///
+/// `max_bits_y` bounds the entries of `y`: `|y[i]| < 2^max_bits_y`; the residuals `y - x.beta` are assumed to satisfy the same bound
+/// (which holds for any sensible fit). The divisions are done with `verified_div`
+///
+/// The response must not be constant
+pub fn r_squared<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    y: &ZkVector<F, PRECISION_BITS>,
+    beta: &ZkVector<F, PRECISION_BITS>,
+    max_bits_y: u32,
+) -> AssignedValue<F> {
+    let n = y.size();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    // sum of squared residuals
+    let r = linear_residual(ctx, fpchip, x, y, beta);
+    let ssr = r._norm_square(ctx, fpchip);
+
+    // total sum of squares
+    let n_q = ctx.load_constant(fpchip.quantization(n as f64));
+    let mean_bits = (max_bits_y + PRECISION_BITS + bit_len(n)) as usize;
+    let y_sum = gate.sum(ctx, y.v.clone());
+    let y_mean = verified_div(ctx, fpchip, y_sum, n_q, mean_bits);
+    let mut y_c: Vec<AssignedValue<F>> = Vec::new();
+    for elem in &y.v {
+        y_c.push(gate.sub(ctx, *elem, y_mean));
+    }
+    let sst = ZkVector::<F, PRECISION_BITS> { v: y_c }._norm_square(ctx, fpchip);
+
+    // both sums of squares are less than n*2^(2*max_bits_y + 2)
+    let ss_bits = (2 * max_bits_y + 2 + PRECISION_BITS + bit_len(n)) as usize;
+    let unexplained = verified_div(ctx, fpchip, ssr, sst, ss_bits);
+    let r2 = gate.sub(ctx, Constant(fpchip.quantization(1.0)), unexplained);
+    return r2;
+}
+
+/// Checks the (regularized) normal equations `(x^T*x + lambda*Id)*beta = x^T*y` up to `err` in each entry,
+/// where `x^T*x` is committed with `honest_prover_mat_mul` (and must be verified with `check_ols_phase1`)
+///
+/// The two sides are compared without any rescaling, at the scale `2^(3*PRECISION_BITS)`
+fn check_normal_equations<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    y: &ZkVector<F, PRECISION_BITS>,
+    beta: &ZkVector<F, PRECISION_BITS>,
+    lambda: f64,
+    err: f64,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>) {
+    assert_eq!(x.num_rows, y.size());
+    assert_eq!(x.num_col, beta.size());
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    let x_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&x);
+    let xt_times_x = honest_prover_mat_mul(ctx, &x_t.matrix, &x.matrix);
+
+    // x^T*x*beta has scale 2^(3*PRECISION_BITS) and x^T*y has scale 2^(2*PRECISION_BITS)
+    let mut lhs = field_mat_vec_mul(ctx, gate, &xt_times_x, &beta.v);
+    if lambda != 0.0 {
+        // lambda*beta has scale 2^(2*PRECISION_BITS)
+        let lambda_q = fpchip.quantization(lambda);
+        for (elem, beta_i) in lhs.iter_mut().zip(beta.v.iter()) {
+            *elem = gate.mul_add(ctx, *beta_i, Constant(lambda_q * quant), *elem);
+        }
+    }
+    let xt_y = field_mat_vec_mul(ctx, gate, &x_t.matrix, &y.v);
+    let xt_y_scaled = field_mat_scalar_mul(ctx, gate, &vec![xt_y], quant);
+
+    // define the triply scaled error
+    let err_scale = BigUint::from((err * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128)
+        * BigUint::from(2u32).pow(PRECISION_BITS);
+    check_mat_diff(ctx, &range, &vec![lhs], &xt_y_scaled, &err_scale);
+
+    return (x_t, xt_times_x);
+}
+
+/// Given a design matrix `x` (`n X p` dimension), a response `y` (`n` dimension) and claimed coefficients `beta` (`p` dimension)
+/// in fixed point representation with `fpchip`, performs the first part of checks that `beta` is the ordinary least squares fit,
+/// i.e., that it satisfies the normal equations `x^T*x*beta = x^T*y` up to `err_ols` in each entry (see `ols_err_calc`)
+///
+/// `x^T*x` is committed with `honest_prover_mat_mul` and verified in `check_ols_phase1`; the normal equations are then compared
+/// without any rescaling, at the scale `2^(3*PRECISION_BITS)`
+///
+/// The entries of `beta` are appended to `make_public`; if `expose_r2` is set, `R^2` is also computed with `r_squared` (using
+/// `max_bits_y`) and appended to `make_public` after them, and returned
+///
+/// Must call `check_ols_phase1` function following this function in the second phase to complete the check
+///
+/// The first two outputs are witnesses to be used for the corresponding variables in `check_ols_phase1`
+///
+/// Leads to about `2*n*p + p^2` cells, plus about `n*p + 90*n` cells for `R^2`
+///
+/// NOTE: the entries of `x^T*x*beta` must be less than `2^PRECISION_BITS` to avoid overflows, which is assumed to be
+/// enforced by the function or program calling this library
+pub fn check_ols_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    y: &ZkVector<F, PRECISION_BITS>,
+    beta: &ZkVector<F, PRECISION_BITS>,
+    err_ols: f64,
+    max_bits_y: u32,
+    expose_r2: bool,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>, Option<AssignedValue<F>>) {
+    let (x_t, xt_times_x) = check_normal_equations(ctx, fpchip, x, y, beta, 0.0, err_ols);
+
+    make_public.extend(beta.v.iter().copied());
+
+    let r2 = if expose_r2 {
+        let r2 = r_squared(ctx, fpchip, x, y, beta, max_bits_y);
+        make_public.push(r2);
+        Some(r2)
+    } else {
+        None
+    };
+
+    return (x_t, xt_times_x, r2);
+}
+
+/// Same as `check_ols_phase0`, but for ridge (Tikhonov regularized) regression with the public regularization parameter `lambda`,
+/// i.e., checks that `beta` satisfies `(x^T*x + lambda*Id)*beta = x^T*y` up to `err_ridge` in each entry (see `ridge_err_calc`)
+///
+/// The entries of `beta` are appended to `make_public`
+///
+/// This is the cheaper of the two ridge routes; see `ridge_from_svd` for the spectral one
+///
+/// Must call `check_ols_phase1` function following this function in the second phase to complete the check
+pub fn check_ridge_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    y: &ZkVector<F, PRECISION_BITS>,
+    beta: &ZkVector<F, PRECISION_BITS>,
+    lambda: f64,
+    err_ridge: f64,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>) {
+    assert!(lambda >= 0.0);
+    let (x_t, xt_times_x) = check_normal_equations(ctx, fpchip, x, y, beta, lambda, err_ridge);
+    make_public.extend(beta.v.iter().copied());
+    return (x_t, xt_times_x);
+}
+
+/// With zk constraints calculates the ridge regression coefficients `beta = v^T*Diag(d/(d^2 + lambda))*u^T*y` from the SVD
+/// `x = u*d*v` of the design matrix `x` (`n X p` dimension) and the response `y` (`n` dimension)
+///
+/// `u_t`, `v_t` and `d` must be verified for `x` with `check_svd_phase0` and `check_svd_phase1` (or with the thin SVD check, since only
+/// the first `min{n, p}` columns of `u` and rows of `v` are used); `max_bits_d` is the same as the one used there
+///
+/// The shrinkage factors `d[i]/(d[i]^2 + lambda)` are computed with `verified_div`, so `lambda` must be at least `2^-PRECISION_BITS`;
+/// the products are computed with `ZkVector::mul`. The entries of `beta` are appended to `make_public`
+///
+/// This is the more stable of the two ridge routes, as it does not depend on the conditioning of `x^T*x`;
+/// leads to about `min{n, p}*(n + p) + 90*(n + p)` cells on top of the SVD check
+pub fn ridge_from_svd<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    y: &ZkVector<F, PRECISION_BITS>,
+    lambda: f64,
+    max_bits_d: u32,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> ZkVector<F, PRECISION_BITS> {
+    let k = d.size();
+    assert!(k <= u_t.num_rows && k <= v_t.num_col);
+    assert_eq!(u_t.num_col, y.size());
+    assert!(lambda >= 2.0_f64.powf(-1.0 * PRECISION_BITS as f64));
+
+    let gate: &GateChip<F> = fpchip.gate();
+
+    // first k columns of u and rows of v
+    let u_t_k: ZkMatrix<F, PRECISION_BITS> =
+        ZkMatrix { matrix: u_t.matrix[..k].to_vec(), num_rows: k, num_col: u_t.num_col };
+    let v_t_k: ZkMatrix<F, PRECISION_BITS> = ZkMatrix {
+        matrix: v_t.matrix.iter().map(|row| row[..k].to_vec()).collect(),
+        num_rows: v_t.num_rows,
+        num_col: k,
+    };
+
+    // shrinkage factors d/(d^2 + lambda); numerator and denominator are less than 2^den_bits
+    let lambda_q = fpchip.quantization(lambda);
+    let lambda_bits = cmp::max(lambda.log2().ceil() as i64, 0) as u32;
+    let den_bits = (cmp::max(2 * max_bits_d, lambda_bits) + PRECISION_BITS + 1) as usize;
+    let mut shrink: Vec<AssignedValue<F>> = Vec::new();
+    for elem in &d.v {
+        let elem_sq = fpchip.qmul(ctx, *elem, *elem);
+        let den = gate.add(ctx, elem_sq, Constant(lambda_q));
+        shrink.push(verified_div(ctx, fpchip, *elem, den, den_bits));
+    }
+
+    let ut_y = y.mul(ctx, fpchip, &u_t_k);
+    let mut w: Vec<AssignedValue<F>> = Vec::new();
+    for (f_i, z_i) in shrink.iter().zip(ut_y.v.iter()) {
+        w.push(fpchip.qmul(ctx, *f_i, *z_i));
+    }
+    let beta = ZkVector::<F, PRECISION_BITS> { v: w }.mul(ctx, fpchip, &v_t_k);
+
+    make_public.extend(beta.v.iter().copied());
+    return beta;
+}
+
+/// Second phase function for checking the ordinary least squares fit (or the ridge fit of `check_ridge_phase0`);
+///
+/// `check_ols_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `x` as used in `check_ols_phase0` and other inputs correspond to the outputs of `check_ols_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// The product is checked with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree `2*p - 2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_ols_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    x_t: &ZkMatrix<F, PRECISION_BITS>,
+    xt_times_x: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(x_t, x, xt_times_x)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Calculates `err_ols` for `check_ols_phase0` from `eps_ols`
+///
+/// `p` is the PRECISION_BITS for the fixed point chip
+///
+/// `size` is the largest dimension of `x` for which the output is to be used
+///
+/// `max_norm`, `max_beta` and `max_y` are the maximum 2-norms of `x` (operator norm), `beta` and `y` for which the output is to be used
+///
+/// `eps_ols` is the entrywise error of `x^T*x*beta - x^T*y` for the (floating point) fit before quantization
+///
+/// Quantizing `x` changes it by at most `size*2^-(p+1)` in operator norm and quantizing `y` and `beta` changes them by at most
+/// `sqrt(size)*2^-(p+1)` in norm; this changes each entry of `x^T*x*beta - x^T*y` by at most
+/// `size*2^-(p+1)*(2*max_norm*max_beta + max_y + max_norm + max_norm^2)`, up to second order terms
+pub fn ols_err_calc(
+    p: u32,
+    size: usize,
+    max_norm: f64,
+    max_beta: f64,
+    max_y: f64,
+    eps_ols: f64,
+) -> f64 {
+    let precision = 2.0_f64.powf(-1.0 * (p as f64 + 1.0));
+    let err_ols = precision
+        * (size as f64)
+        * (2.0 * max_norm * max_beta + max_y + max_norm + max_norm * max_norm)
+        + eps_ols;
+    return err_ols;
+}
+
+/// Calculates `err_ridge` for `check_ridge_phase0` from `eps_ridge`
+///
+/// The inputs are the same as for `ols_err_calc`, along with the regularization parameter `lambda`
+///
+/// On top of the error terms in `ols_err_calc`, quantizing `beta` and `lambda` changes each entry of `lambda*beta` by at most
+/// `(lambda + max_beta)*2^-(p+1)`, up to second order terms
+pub fn ridge_err_calc(
+    p: u32,
+    size: usize,
+    max_norm: f64,
+    max_beta: f64,
+    max_y: f64,
+    lambda: f64,
+    eps_ridge: f64,
+) -> f64 {
+    let precision = 2.0_f64.powf(-1.0 * (p as f64 + 1.0));
+    let err_ridge = ols_err_calc(p, size, max_norm, max_beta, max_y, eps_ridge)
+        + precision * (lambda + max_beta);
+    return err_ridge;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+    use crate::svd::{check_svd_phase0, check_svd_phase1, err_calc};
+
+    /// Design matrix with an intercept column and response; the OLS fit is `beta = (1.1, 1.6)`, with residuals
+    /// `(-0.1, 0.3, -0.3, 0.1)` and `R^2 = 1 - 0.2/13`
+    fn ols_example() -> (Vec<Vec<f64>>, Vec<f64>) {
+        let x = vec![vec![1.0, 0.0], vec![1.0, 1.0], vec![1.0, 2.0], vec![1.0, 3.0]];
+        let y = vec![1.0, 3.0, 4.0, 6.0];
+        return (x, y);
+    }
+
+    /// Runs both phases of the OLS check of `beta` for `ols_example` with `R^2` exposed; if `forge` is set, the last entry of
+    /// the committed product `x^T*x` is forged before the second phase
+    ///
+    /// Outputs whether `MockProver` accepts, along with the dequantized `R^2`
+    fn ols_accepts(beta: Vec<f64>, forge: bool) -> (bool, f64) {
+        let (x, y) = ols_example();
+        let mut r2_dq = 0.0;
+        let accepted = mock_accepts(|ctx, fpchip| {
+            let x = ZkMatrix::new(ctx, fpchip, &x);
+            let y = ZkVector::new(ctx, fpchip, &y);
+            let beta = ZkVector::new(ctx, fpchip, &beta);
+            let err_ols = ols_err_calc(PRECISION_BITS, 4, 5.0, 2.0, 8.0, 1e-9);
+            let mut make_public: Vec<AssignedValue<Fr>> = Vec::new();
+            let (x_t, xt_times_x, r2) =
+                check_ols_phase0(ctx, fpchip, &x, &y, &beta, err_ols, 3, true, &mut make_public);
+            let r2 = r2.unwrap();
+            // the coefficients followed by R^2
+            assert_eq!(make_public.len(), 3);
+            assert_eq!(make_public[2].value(), r2.value());
+            r2_dq = fpchip.dequantization(*r2.value());
+
+            let xt_times_x = if forge { forge_last_entry(ctx, &xt_times_x) } else { xt_times_x };
+            let init_rand = mock_init_rand(ctx);
+            check_ols_phase1(ctx, fpchip, &x, &x_t, &xt_times_x, &init_rand, 2);
+        });
+        return (accepted, r2_dq);
+    }
+
+    #[test]
+    fn test_ols() {
+        let (accepted, r2) = ols_accepts(vec![1.1, 1.6], false);
+        assert!(accepted);
+        assert!((r2 - (1.0 - 0.2 / 13.0)).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_ols_forged_coefficients() {
+        // (x^T*x)*(0, 0.01) = (0.06, 0.14) is far above err_ols
+        assert!(!ols_accepts(vec![1.1, 1.61], false).0);
+        assert!(!ols_accepts(vec![1.0, 1.6], false).0);
+    }
+
+    #[test]
+    fn test_ols_forged_product() {
+        assert!(!ols_accepts(vec![1.1, 1.6], true).0);
+    }
+
+    #[test]
+    fn test_r_squared() {
+        assert!(mock_accepts(|ctx, fpchip| {
+            let (x, y) = ols_example();
+            let x = ZkMatrix::new(ctx, fpchip, &x);
+            let y = ZkVector::new(ctx, fpchip, &y);
+            // a perfect fit of y = 1 + 2*x[1]
+            let y_exact = ZkVector::new(ctx, fpchip, &vec![1.0, 3.0, 5.0, 7.0]);
+            let beta = ZkVector::new(ctx, fpchip, &vec![1.0, 2.0]);
+            let r2 = r_squared(ctx, fpchip, &x, &y_exact, &beta, 3);
+            fpchip.gate().assert_is_const(ctx, &r2, &fpchip.quantization(1.0));
+            // the zero model explains less than nothing: R^2 = 1 - 62/13
+            let zero = ZkVector::new(ctx, fpchip, &vec![0.0, 0.0]);
+            let r2 = r_squared(ctx, fpchip, &x, &y, &zero, 3);
+            assert!((fpchip.dequantization(*r2.value()) - (1.0 - 62.0 / 13.0)).abs() < 1e-7);
+        }));
+    }
+
+    /// Returns `(x, u, d, v, y)` for a `4 X 2` design matrix `x = u*Diag(d)*v` (with the first two columns of `u`) and a response `y`
+    fn ridge_example() -> (Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<f64>, Vec<Vec<f64>>, Vec<f64>) {
+        let x = vec![vec![0.4, 2.2], vec![2.0, 1.0], vec![0.4, 2.2], vec![2.0, 1.0]];
+        let u = vec![
+            vec![0.5, 0.5, 0.5, 0.5],
+            vec![0.5, -0.5, 0.5, -0.5],
+            vec![0.5, 0.5, -0.5, -0.5],
+            vec![0.5, -0.5, -0.5, 0.5],
+        ];
+        let d = vec![4.0, 2.0];
+        let v = vec![vec![0.6, 0.8], vec![-0.8, 0.6]];
+        let y = vec![1.0, 2.0, 3.0, 4.0];
+        return (x, u, d, v, y);
+    }
+
+    /// Reference ridge coefficients `v^T*Diag(d/(d^2 + lambda))*u^T*y` for `ridge_example`, computed in f64
+    fn ridge_beta_f64(lambda: f64) -> Vec<f64> {
+        let (_, u, d, v, y) = ridge_example();
+        let w: Vec<f64> = (0..2)
+            .map(|k| {
+                let ut_y: f64 = (0..4).map(|i| u[i][k] * y[i]).sum();
+                d[k] / (d[k] * d[k] + lambda) * ut_y
+            })
+            .collect();
+        return (0..2).map(|j| (0..2).map(|k| v[k][j] * w[k]).sum()).collect();
+    }
+
+    /// Runs both phases of the direct ridge check of `beta` for `ridge_example` with `lambda = 1`; if `forge` is set, the last entry
+    /// of the committed product `x^T*x` is forged before the second phase
+    fn ridge_accepts(beta: Vec<f64>, forge: bool) -> bool {
+        let (x, _, _, _, y) = ridge_example();
+        return mock_accepts(|ctx, fpchip| {
+            let x = ZkMatrix::new(ctx, fpchip, &x);
+            let y = ZkVector::new(ctx, fpchip, &y);
+            let beta = ZkVector::new(ctx, fpchip, &beta);
+            let err_ridge = ridge_err_calc(PRECISION_BITS, 4, 5.0, 2.0, 8.0, 1.0, 1e-9);
+            let mut make_public: Vec<AssignedValue<Fr>> = Vec::new();
+            let (x_t, xt_times_x) =
+                check_ridge_phase0(ctx, fpchip, &x, &y, &beta, 1.0, err_ridge, &mut make_public);
+            let xt_times_x = if forge { forge_last_entry(ctx, &xt_times_x) } else { xt_times_x };
+            let init_rand = mock_init_rand(ctx);
+            check_ols_phase1(ctx, fpchip, &x, &x_t, &xt_times_x, &init_rand, 2);
+        });
+    }
+
+    /// Verifies the SVD `u`, `d`, `v` of `ridge_example` and runs `ridge_from_svd` with `lambda = 1`; if `forge` is set, the last
+    /// entry of the committed product `x*v^T` is forged before the second phase. If `check_direct` is set, the output is also
+    /// checked with `check_ridge_phase0` (with `eps_ridge` covering the rounding of the SVD route)
+    ///
+    /// Outputs whether `MockProver` accepts, along with the dequantized coefficients
+    fn svd_ridge_accepts(d: Vec<f64>, forge: bool, check_direct: bool) -> (bool, Vec<f64>) {
+        let (x, u, _, v, y) = ridge_example();
+        let mut beta_dq = Vec::new();
+        let accepted = mock_accepts(|ctx, fpchip| {
+            let x = ZkMatrix::new(ctx, fpchip, &x);
+            let u = ZkMatrix::new(ctx, fpchip, &u);
+            let d = ZkVector::new(ctx, fpchip, &d);
+            let v = ZkMatrix::new(ctx, fpchip, &v);
+            let y = ZkVector::new(ctx, fpchip, &y);
+            let (err_svd, err_u) = err_calc(PRECISION_BITS, 4, 5.0, 0.0, 0.0);
+            let (u_t, v_t, x_times_vt, u_times_ut, v_times_vt) =
+                check_svd_phase0(ctx, fpchip, &x, &u, &v, &d, err_svd, err_u, 3);
+            let x_times_vt = if forge { forge_last_entry(ctx, &x_times_vt) } else { x_times_vt };
+
+            let mut make_public: Vec<AssignedValue<Fr>> = Vec::new();
+            let beta = ridge_from_svd(ctx, fpchip, &u_t, &v_t, &d, &y, 1.0, 3, &mut make_public);
+            assert_eq!(make_public.len(), 2);
+            beta_dq = beta.dequantize(fpchip);
+
+            let direct = if check_direct {
+                let err_ridge = ridge_err_calc(PRECISION_BITS, 4, 5.0, 2.0, 8.0, 1.0, 1e-7);
+                Some(check_ridge_phase0(
+                    ctx,
+                    fpchip,
+                    &x,
+                    &y,
+                    &beta,
+                    1.0,
+                    err_ridge,
+                    &mut make_public,
+                ))
+            } else {
+                None
+            };
+
+            let init_rand = mock_init_rand(ctx);
+            check_svd_phase1(
+                ctx,
+                fpchip,
+                &x,
+                &u,
+                &v,
+                &u_t,
+                &v_t,
+                &x_times_vt,
+                &u_times_ut,
+                &v_times_vt,
+                &init_rand,
+            );
+            if let Some((x_t, xt_times_x)) = direct {
+                check_ols_phase1(ctx, fpchip, &x, &x_t, &xt_times_x, &init_rand, 2);
+            }
+        });
+        return (accepted, beta_dq);
+    }
+
+    #[test]
+    fn test_ridge_direct() {
+        assert!(ridge_accepts(ridge_beta_f64(1.0), false));
+    }
+
+    #[test]
+    fn test_ridge_direct_forged() {
+        let mut beta = ridge_beta_f64(1.0);
+        beta[1] += 0.01;
+        assert!(!ridge_accepts(beta, false));
+        // the OLS fit does not satisfy the regularized normal equations
+        assert!(!ridge_accepts(ridge_beta_f64(0.0), false));
+        assert!(!ridge_accepts(ridge_beta_f64(1.0), true));
+    }
+
+    #[test]
+    fn test_ridge_from_svd() {
+        let (_, _, d, _, _) = ridge_example();
+        let (accepted, beta) = svd_ridge_accepts(d, false, false);
+        assert!(accepted);
+        for (elem, expected) in beta.iter().zip(ridge_beta_f64(1.0).iter()) {
+            assert!((elem - expected).abs() < 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_ridge_from_svd_forged() {
+        // a forged singular value changes the shrinkage factors, but fails the SVD check
+        assert!(!svd_ridge_accepts(vec![4.0, 2.5], false, false).0);
+        let (_, _, d, _, _) = ridge_example();
+        assert!(!svd_ridge_accepts(d, true, false).0);
+    }
+
+    #[test]
+    fn test_ridge_routes_agree() {
+        // the coefficients computed from the SVD satisfy the regularized normal equations
+        let (_, _, d, _, _) = ridge_example();
+        assert!(svd_ridge_accepts(d, false, true).0);
+    }
+}
@@ -0,0 +1,194 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateInstructions, RangeInstructions};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::{
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+use num_bigint::BigUint;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+
+/// With zk constraints calculates the residual `a.x - b` of the linear system `a.x = b` with the scale `2^(2*PRECISION_BITS)`,
+/// i.e., without rescaling it
+///
+/// Leads to about `N*K + N` constraints for `a` of size `N X K`
+fn linear_residual_unscaled<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    b: &ZkVector<F, PRECISION_BITS>,
+    x: &ZkVector<F, PRECISION_BITS>,
+) -> Vec<AssignedValue<F>> {
+    assert_eq!(a.num_col, x.size());
+    assert_eq!(a.num_rows, b.size());
+
+    let gate = fpchip.gate();
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+
+    let mut r_s: Vec<AssignedValue<F>> = Vec::new();
+    for (row, b_i) in a.matrix.iter().zip(b.v.iter()) {
+        // row.x is doubly scaled; so b_i is scaled once more before subtracting
+        let row_times_x_s =
+            gate.inner_product(ctx, row.clone(), x.v.iter().map(|elem| Existing(*elem)));
+        r_s.push(gate.mul_add(ctx, *b_i, Constant(-quant), row_times_x_s));
+    }
+    return r_s;
+}
+
+/// With zk constraints calculates the residual `a.x - b` of the linear system `a.x = b` and outputs it
+///
+/// Each entry is computed as a field inner product followed by a single rescaling, as in `ZkVector::inner_product`
+///
+/// Leads to about `N*K + 90*N` constraints for `a` of size `N X K`
+pub fn linear_residual<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    b: &ZkVector<F, PRECISION_BITS>,
+    x: &ZkVector<F, PRECISION_BITS>,
+) -> ZkVector<F, PRECISION_BITS> {
+    let r_s = linear_residual_unscaled(ctx, fpchip, a, b, x);
+    return rescale_vector(ctx, fpchip, &r_s);
+}
+
+/// Rescales each entry of the vector `v_s` once with `signed_div_scale`
+fn rescale_vector<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    v_s: &Vec<AssignedValue<F>>,
+) -> ZkVector<F, PRECISION_BITS> {
+    let mut v: Vec<AssignedValue<F>> = Vec::new();
+    for elem in v_s {
+        let (elem, _) = fpchip.signed_div_scale(ctx, *elem);
+        v.push(elem);
+    }
+    return ZkVector { v };
+}
+
+/// Constrains the square of the norm of the vector `v_s`, which has the scale `2^(2*PRECISION_BITS)`, to be less than `tol^2`
+///
+/// The square is computed in the field with the scale `2^(4*PRECISION_BITS)` and compared without any rescaling, so
+/// `4*PRECISION_BITS` plus the number of bits of the square of the norm must be less than the number of bits of the field
+fn check_norm_less_than<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    v_s: &Vec<AssignedValue<F>>,
+    tol: f64,
+) {
+    let norm_sq_s =
+        fpchip.gate().inner_product(ctx, v_s.clone(), v_s.iter().map(|elem| Existing(*elem)));
+    // quantization of tol^2 with the scale 2^(4*PRECISION_BITS)
+    let tol_sq_q =
+        BigUint::from((tol * tol * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128)
+            * BigUint::from(2u32).pow(2 * PRECISION_BITS);
+    fpchip.range_gate().check_big_less_than_safe(ctx, norm_sq_s, tol_sq_q);
+}
+
+/// Given a matrix `a` (`N X K` dimension), a vector `b` (`N` dimension) and a claimed solution `x` (`K` dimension)
+/// in fixed point representation with `fpchip`, checks that `x` solves the linear system `a.x = b`, i.e.,
+/// that the residual satisfies $\Vert a x - b \Vert_2 <$ `tol`
+///
+/// Outputs the residual `a.x - b` (see `linear_residual`); the norm is checked on the residual before it is rescaled, so the
+/// check does not depend on the rounding of the output
+///
+/// NOTE: the fixed point chip does not check for overflows, so the entries of `a` and `x` need to be bounded
+/// such that the entries of `a.x` are less than `2^PRECISION_BITS`. This bound is assumed to be enforced by the function
+/// or program calling this library
+pub fn check_linear_solve<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    b: &ZkVector<F, PRECISION_BITS>,
+    x: &ZkVector<F, PRECISION_BITS>,
+    tol: f64,
+) -> ZkVector<F, PRECISION_BITS> {
+    let r_s = linear_residual_unscaled(ctx, fpchip, a, b, x);
+    check_norm_less_than(ctx, fpchip, &r_s, tol);
+    return rescale_vector(ctx, fpchip, &r_s);
+}
+
+/// Given a (possibly rectangular) matrix `a` (`N X K` dimension), a vector `b` (`N` dimension) and a claimed least
+/// squares solution `x` (`K` dimension) in fixed point representation with `fpchip`, checks that `x` satisfies the
+/// normal equations `a^T.(a.x - b) = 0` up to $\Vert a^T (a x - b) \Vert_2 <$ `tol`
+///
+/// Outputs the residual of the normal equations `a^T.(a.x - b)`; the norm is checked on the residual before its last rescaling
+///
+/// Leads to about `2*N*K + 180*K` constraints
+///
+/// NOTE: the same bounds as for `check_linear_solve` are assumed to be enforced by the caller, and additionally the entries
+/// of `a^T.(a.x - b)` need to be less than `2^PRECISION_BITS`
+pub fn check_least_squares<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    a: &ZkMatrix<F, PRECISION_BITS>,
+    b: &ZkVector<F, PRECISION_BITS>,
+    x: &ZkVector<F, PRECISION_BITS>,
+    tol: f64,
+) -> ZkVector<F, PRECISION_BITS> {
+    let r_s = linear_residual_unscaled(ctx, fpchip, a, b, x);
+    let a_t = ZkMatrix::transpose_matrix(a);
+    // a^T.(a.x - b) has scale 2^(3*PRECISION_BITS); a single rescaling brings it to the scale of check_norm_less_than
+    let normal_r_s = field_mat_vec_mul(ctx, fpchip.gate(), &a_t.matrix, &r_s);
+    let normal_r_s = rescale_vector(ctx, fpchip, &normal_r_s).v;
+    check_norm_less_than(ctx, fpchip, &normal_r_s, tol);
+    return rescale_vector(ctx, fpchip, &normal_r_s);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+    use halo2_base::gates::builder::GateThreadBuilder;
+
+    /// Checks `x` as the solution of `[[2, 1], [1, 3]].x = [3, 5]` up to `tol`
+    fn solve_accepts(x: Vec<f64>, tol: f64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a = ZkMatrix::new(ctx, fpchip, &vec![vec![2.0, 1.0], vec![1.0, 3.0]]);
+            let b = ZkVector::new(ctx, fpchip, &vec![3.0, 5.0]);
+            let x = ZkVector::new(ctx, fpchip, &x);
+            check_linear_solve(ctx, fpchip, &a, &b, &x, tol);
+        });
+    }
+
+    /// Checks `x` as the least squares solution of `[[1, 0], [0, 1], [1, 1]].x = [1, 2, 2]` up to `tol`
+    fn least_squares_accepts(x: Vec<f64>, tol: f64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let a =
+                ZkMatrix::new(ctx, fpchip, &vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]]);
+            let b = ZkVector::new(ctx, fpchip, &vec![1.0, 2.0, 2.0]);
+            let x = ZkVector::new(ctx, fpchip, &x);
+            check_least_squares(ctx, fpchip, &a, &b, &x, tol);
+        });
+    }
+
+    #[test]
+    fn test_linear_solve_honest() {
+        assert!(solve_accepts(vec![0.8, 1.4], 1e-6));
+        assert!(least_squares_accepts(vec![2.0 / 3.0, 5.0 / 3.0], 1e-6));
+    }
+
+    #[test]
+    fn test_linear_solve_above_tolerance() {
+        // the residual is (0.01, 0.03), whose norm is about 0.0316
+        assert!(solve_accepts(vec![0.8, 1.41], 0.04));
+        assert!(!solve_accepts(vec![0.8, 1.41], 0.03));
+        // the residual of the normal equations is about (0.067, 0.033)
+        assert!(!least_squares_accepts(vec![0.7, 5.0 / 3.0], 0.05));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_linear_solve_dimension_mismatch() {
+        let mut builder = GateThreadBuilder::<Fr>::new(false);
+        let ctx = builder.main(0);
+        let fpchip = FixedPointChip::<Fr, PRECISION_BITS>::default(12);
+        let a = ZkMatrix::new(ctx, &fpchip, &vec![vec![2.0, 1.0], vec![1.0, 3.0]]);
+        let b = ZkVector::new(ctx, &fpchip, &vec![3.0, 5.0]);
+        let x = ZkVector::new(ctx, &fpchip, &vec![0.8, 1.4, 0.0]);
+        check_linear_solve(ctx, &fpchip, &a, &b, &x, 1e-6);
+    }
+}
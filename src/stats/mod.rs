@@ -0,0 +1,532 @@
+// #![allow(warnings)]
+#![allow(dead_code)]
+// #[allow(unused_imports)]
+use halo2_base::gates::{GateChip, GateInstructions};
+use halo2_base::utils::BigPrimeField;
+use halo2_base::AssignedValue;
+use halo2_base::Context;
+use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
+
+use super::matrix::*;
+use super::svd::*;
+
+/// With zk constraints calculates the mean of each column of the data matrix `x` (`n X p` dimension, one sample per row)
+///
+/// Each mean is the column sum divided by `n` with `verified_div`; `max_bits_x` bounds the entries of `x`, i.e., `|x[i][j]| < 2^max_bits_x`,
+/// which is assumed to be enforced by the caller
+///
+/// Leads to about `n*p` cells for the sums and `p` divisions
+pub fn column_means<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    max_bits_x: u32,
+) -> ZkVector<F, PRECISION_BITS> {
+    let gate: &GateChip<F> = fpchip.gate();
+    let n = x.num_rows;
+    let n_q = ctx.load_constant(fpchip.quantization(n as f64));
+    // the column sums are less than n*2^(max_bits_x + PRECISION_BITS) and so is n_q
+    let max_bits = (max_bits_x + PRECISION_BITS + bit_len(n)) as usize;
+
+    let mut means: Vec<AssignedValue<F>> = Vec::new();
+    for j in 0..x.num_col {
+        let col_sum = gate.sum(ctx, (0..n).map(|i| x.matrix[i][j]).collect::<Vec<_>>());
+        means.push(verified_div(ctx, fpchip, col_sum, n_q, max_bits));
+    }
+    return ZkVector { v: means };
+}
+
+/// Subtracts `means[j]` from each entry of the column `j` of `x` and returns the constrained output
+///
+/// Leads to `n*p` cells
+pub fn center_columns<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    means: &ZkVector<F, PRECISION_BITS>,
+) -> ZkMatrix<F, PRECISION_BITS> {
+    assert_eq!(x.num_col, means.size());
+    let gate: &GateChip<F> = fpchip.gate();
+    let mut x_c: Vec<Vec<AssignedValue<F>>> = Vec::new();
+    for row in &x.matrix {
+        let mut new_row: Vec<AssignedValue<F>> = Vec::new();
+        for (elem, mean) in row.iter().zip(means.v.iter()) {
+            new_row.push(gate.sub(ctx, *elem, *mean));
+        }
+        x_c.push(new_row);
+    }
+    return ZkMatrix { matrix: x_c, num_rows: x.num_rows, num_col: x.num_col };
+}
+
+/// Given a data matrix `x` (`n X p` dimension, one sample per row) in fixed point representation with `fpchip`, performs the first
+/// part of the computation of the Gram matrix `x^T*x` (`p X p` dimension)
+///
+/// The unscaled product is committed with `honest_prover_mat_mul` and rescaled once with `ZkMatrix::rescale_matrix`
+///
+/// Outputs the transpose of `x`, the unscaled product and the Gram matrix; the first two are witnesses to be used for the
+/// corresponding variables in `check_gram_phase1`, which must be called in the second phase to complete the check
+///
+/// Leads to about `n*p + 94*p^2` cells
+pub fn check_gram_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+) -> (ZkMatrix<F, PRECISION_BITS>, Vec<Vec<AssignedValue<F>>>, ZkMatrix<F, PRECISION_BITS>) {
+    let x_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&x);
+    let xt_times_x = honest_prover_mat_mul(ctx, &x_t.matrix, &x.matrix);
+    let gram = ZkMatrix::rescale_matrix(ctx, fpchip, &xt_times_x);
+    return (x_t, xt_times_x, gram);
+}
+
+/// Second phase function for computing the Gram matrix;
+///
+/// `check_gram_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `x` as used in `check_gram_phase0` and other inputs correspond to the outputs of `check_gram_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// The product is checked with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree `2*p - 2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_gram_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    x_t: &ZkMatrix<F, PRECISION_BITS>,
+    xt_times_x: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(x_t, x, xt_times_x)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Given a data matrix `x` (`n X p` dimension, one sample per row) in fixed point representation with `fpchip`, performs the first
+/// part of the computation of the sample covariance matrix `x_c^T*x_c/(n-1)` (`p X p` dimension), where `x_c` is the centered data
+///
+/// Computes the column means (see `column_means`) and `x_c`, computes the Gram matrix of `x_c` with `check_gram_phase0` and divides it
+/// by `n-1` with `verified_div`; `max_bits_x` is as in `column_means`
+///
+/// Outputs the column means, `x_c`, the transpose of `x_c`, the unscaled product `x_c^T*x_c` and the covariance matrix;
+/// `check_covariance_phase1` must be called in the second phase to complete the check
+///
+/// Leads to about `2*n*p + 94*p^2` cells and `p*(p+1)/2` divisions (the covariance matrix is symmetric)
+pub fn check_covariance_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    max_bits_x: u32,
+) -> (
+    ZkVector<F, PRECISION_BITS>,
+    ZkMatrix<F, PRECISION_BITS>,
+    ZkMatrix<F, PRECISION_BITS>,
+    Vec<Vec<AssignedValue<F>>>,
+    ZkMatrix<F, PRECISION_BITS>,
+) {
+    let n = x.num_rows;
+    let p = x.num_col;
+    assert!(n >= 2);
+
+    let means = column_means(ctx, fpchip, x, max_bits_x);
+    let x_c = center_columns(ctx, fpchip, x, &means);
+    let (x_c_t, xct_times_xc, gram) = check_gram_phase0(ctx, fpchip, &x_c);
+
+    // entries of x_c are less than 2^(max_bits_x + 1), so the entries of the Gram matrix are less than n*2^(2*max_bits_x + 2)
+    let max_bits = (2 * max_bits_x + 2 + PRECISION_BITS + bit_len(n)) as usize;
+    let n_minus_one_q = ctx.load_constant(fpchip.quantization((n - 1) as f64));
+    let mut cov: Vec<Vec<AssignedValue<F>>> = vec![Vec::new(); p];
+    for i in 0..p {
+        for j in 0..p {
+            let elem = if j < i {
+                cov[j][i]
+            } else {
+                verified_div(ctx, fpchip, gram.matrix[i][j], n_minus_one_q, max_bits)
+            };
+            cov[i].push(elem);
+        }
+    }
+    let cov = ZkMatrix { matrix: cov, num_rows: p, num_col: p };
+
+    return (means, x_c, x_c_t, xct_times_xc, cov);
+}
+
+/// Second phase function for computing the covariance matrix; same as `check_gram_phase1` for the centered data `x_c`
+/// output by `check_covariance_phase0`
+///
+/// `init_rand` must be a commitment of all the inputs to this function (committing to `x` instead of `x_c` is enough)
+pub fn check_covariance_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x_c: &ZkMatrix<F, PRECISION_BITS>,
+    x_c_t: &ZkMatrix<F, PRECISION_BITS>,
+    xct_times_xc: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    check_gram_phase1(ctx, fpchip, x_c, x_c_t, xct_times_xc, init_rand, num_reps);
+}
+
+/// Given a covariance matrix `cov` (`p X p` dimension) output by `check_covariance_phase0`, outputs the vector of standard deviations
+/// and the correlation matrix `cov[i][j]/(std[i]*std[j])`
+///
+/// The standard deviations are computed with `verified_sqrt` and the correlations with `verified_div`; the diagonal of the
+/// correlation matrix is set to exactly `1.0`. `max_bits_cov` bounds the entries of `cov`: `|cov[i][j]| < 2^max_bits_cov`
+///
+/// The standard deviations must be large enough that `std[i]*std[j] >= 2^-PRECISION_BITS`; in particular, no column of the data can be constant
+pub fn correlation<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    cov: &ZkMatrix<F, PRECISION_BITS>,
+    max_bits_cov: u32,
+) -> (ZkVector<F, PRECISION_BITS>, ZkMatrix<F, PRECISION_BITS>) {
+    let p = cov.num_rows;
+    assert_eq!(cov.num_rows, cov.num_col);
+
+    let max_bits = (max_bits_cov + PRECISION_BITS) as usize;
+    let mut std: Vec<AssignedValue<F>> = Vec::new();
+    for i in 0..p {
+        std.push(verified_sqrt(ctx, fpchip, cov.matrix[i][i], max_bits));
+    }
+
+    let one = ctx.load_constant(fpchip.quantization(1.0));
+    let mut corr: Vec<Vec<AssignedValue<F>>> = vec![Vec::new(); p];
+    for i in 0..p {
+        for j in 0..p {
+            let elem = if j < i {
+                corr[j][i]
+            } else if j == i {
+                one
+            } else {
+                let std_prod = fpchip.qmul(ctx, std[i], std[j]);
+                verified_div(ctx, fpchip, cov.matrix[i][j], std_prod, max_bits)
+            };
+            corr[i].push(elem);
+        }
+    }
+
+    return (ZkVector { v: std }, ZkMatrix { matrix: corr, num_rows: p, num_col: p });
+}
+
+/// Constrained outputs of `check_pca_phase0`
+#[derive(Clone)]
+pub struct PcaOutput<F: BigPrimeField, const PRECISION_BITS: u32> {
+    /// Column means of the data matrix
+    pub means: ZkVector<F, PRECISION_BITS>,
+    /// Centered data matrix
+    pub x_c: ZkMatrix<F, PRECISION_BITS>,
+    /// Top `k` principal directions (loadings), one per row
+    pub loadings: ZkMatrix<F, PRECISION_BITS>,
+    /// Top `k` explained variances `d[i]^2/(n-1)`
+    pub variances: ZkVector<F, PRECISION_BITS>,
+    /// Fraction of the total variance explained by the top `k` components
+    pub ratio: AssignedValue<F>,
+}
+
+/// Given a data matrix `x` (`n X p` dimension, one sample per row) and the thin SVD `u` (`n X q`), `d` (`q`), `v` (`q X p`)
+/// of the centered data, where `q = min{n, p}`, performs the first part of checks of the principal component analysis of `x`
+///
+/// Computes the column means (see `column_means`) and the centered data `x_c`, and checks that `x_c = u*d*v` with `check_thin_svd_phase0`;
+/// the thin SVD is used since `n` is usually much larger than `p`. `err_svd`, `err_u` and `max_bits_d` are as in `check_thin_svd_phase0`
+/// and `max_bits_x` is as in `column_means`
+///
+/// Outputs the constrained `PcaOutput`: the top `k` loadings are the first `k` rows of `v`, the explained variances are `d[i]^2/(n-1)`
+/// for `i < k`, and the explained variance ratio is `(d[0]^2 + ... + d[k-1]^2)/(d[0]^2 + ... + d[q-1]^2)`; the divisions are done
+/// with `verified_div`, so `2*(2*max_bits_d + PRECISION_BITS + log2(n)) + PRECISION_BITS` must be less than the number of bits of the field
+/// (and the centered data must not be all zero)
+///
+/// The remaining outputs are witnesses to be used for the corresponding variables in `check_pca_phase1`, which must be called
+/// in the second phase to complete the check
+pub fn check_pca_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    x: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    k: usize,
+    err_svd: f64,
+    err_u: f64,
+    max_bits_x: u32,
+    max_bits_d: u32,
+) -> (
+    PcaOutput<F, PRECISION_BITS>,
+    ZkMatrix<F, PRECISION_BITS>,
+    ZkMatrix<F, PRECISION_BITS>,
+    Vec<Vec<AssignedValue<F>>>,
+    Vec<Vec<AssignedValue<F>>>,
+    Vec<Vec<AssignedValue<F>>>,
+) {
+    let n = x.num_rows;
+    assert!(n >= 2);
+    assert!(k >= 1 && k <= d.size());
+
+    let gate: &GateChip<F> = fpchip.gate();
+
+    let means = column_means(ctx, fpchip, x, max_bits_x);
+    let x_c = center_columns(ctx, fpchip, x, &means);
+
+    let (u_t, v_t, m_prod, ut_times_u, v_times_vt) =
+        check_thin_svd_phase0(ctx, fpchip, &x_c, u, v, d, err_svd, err_u, max_bits_d);
+
+    let loadings: ZkMatrix<F, PRECISION_BITS> =
+        ZkMatrix { matrix: v.matrix[..k].to_vec(), num_rows: k, num_col: v.num_col };
+
+    let mut d_sq: Vec<AssignedValue<F>> = Vec::new();
+    for elem in &d.v {
+        d_sq.push(fpchip.qmul(ctx, *elem, *elem));
+    }
+
+    // d_sq entries are less than 2^(2*max_bits_d + PRECISION_BITS), so their sum is less than n times that
+    let max_bits = (2 * max_bits_d + PRECISION_BITS + bit_len(n)) as usize;
+    let n_minus_one_q = ctx.load_constant(fpchip.quantization((n - 1) as f64));
+    let mut variances: Vec<AssignedValue<F>> = Vec::new();
+    for elem in &d_sq[..k] {
+        variances.push(verified_div(ctx, fpchip, *elem, n_minus_one_q, max_bits));
+    }
+
+    let top_k_sq = gate.sum(ctx, d_sq[..k].to_vec());
+    let total_sq = gate.sum(ctx, d_sq.clone());
+    let ratio = verified_div(ctx, fpchip, top_k_sq, total_sq, max_bits);
+
+    let pca = PcaOutput { means, x_c, loadings, variances: ZkVector { v: variances }, ratio };
+    return (pca, u_t, v_t, m_prod, ut_times_u, v_times_vt);
+}
+
+/// Second phase function for checking the principal component analysis;
+///
+/// `check_pca_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `u`, `v` as used in `check_pca_phase0` and other inputs correspond to the outputs of `check_pca_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+/// (committing to `x` instead of `pca.x_c` is enough, since the latter is computed from the former)
+///
+/// See `check_thin_svd_phase1` for `num_reps`
+pub fn check_pca_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    pca: &PcaOutput<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_prod: &Vec<Vec<AssignedValue<F>>>,
+    ut_times_u: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    check_thin_svd_phase1(
+        ctx, fpchip, &pca.x_c, u, v, u_t, v_t, m_prod, ut_times_u, v_times_vt, init_rand, num_reps,
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+
+    /// Returns `(x, u, d, v)` for a `4 X 2` data matrix `x` with column means `(1, 2)`, whose centered data is `u*Diag(d)*v`;
+    /// the principal directions are the rows of `v`, with explained variances `16/3` and `4/3`
+    fn pca_example() -> (Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<f64>, Vec<Vec<f64>>) {
+        let x = vec![vec![1.4, 4.2], vec![3.0, 3.0], vec![-1.0, 1.0], vec![0.6, -0.2]];
+        let u = vec![vec![0.5, 0.5], vec![0.5, -0.5], vec![-0.5, 0.5], vec![-0.5, -0.5]];
+        let d = vec![4.0, 2.0];
+        let v = vec![vec![0.6, 0.8], vec![-0.8, 0.6]];
+        return (x, u, d, v);
+    }
+
+    /// Runs both phases of the PCA check of `x` with the top component, for the thin SVD `u`, `d`, `v` of its centered data;
+    /// if `forge` is set, the last entry of the committed product `x_c*v^T` is forged before the second phase
+    ///
+    /// Outputs whether `MockProver` accepts, along with the dequantized means, loadings, variances and ratio
+    fn pca_accepts(
+        u: Vec<Vec<f64>>,
+        d: Vec<f64>,
+        v: Vec<Vec<f64>>,
+        forge: bool,
+    ) -> (bool, Vec<f64>, Vec<Vec<f64>>, Vec<f64>, f64) {
+        let (x, _, _, _) = pca_example();
+        let mut values = (Vec::new(), Vec::new(), Vec::new(), 0.0);
+        let accepted = mock_accepts(|ctx, fpchip| {
+            let x = ZkMatrix::new(ctx, fpchip, &x);
+            let u = ZkMatrix::new(ctx, fpchip, &u);
+            let d = ZkVector::new(ctx, fpchip, &d);
+            let v = ZkMatrix::new(ctx, fpchip, &v);
+            let (err_svd, err_u) = err_calc(PRECISION_BITS, 4, 5.0, 1e-9, 0.0);
+            let (pca, u_t, v_t, m_prod, ut_times_u, v_times_vt) =
+                check_pca_phase0(ctx, fpchip, &x, &u, &v, &d, 1, err_svd, err_u, 3, 3);
+            let m_prod = if forge { forge_last_entry(ctx, &m_prod) } else { m_prod };
+            values = (
+                pca.means.dequantize(fpchip),
+                pca.loadings.dequantize(fpchip),
+                pca.variances.dequantize(fpchip),
+                fpchip.dequantization(*pca.ratio.value()),
+            );
+            let init_rand = mock_init_rand(ctx);
+            check_pca_phase1(
+                ctx,
+                fpchip,
+                &pca,
+                &u,
+                &v,
+                &u_t,
+                &v_t,
+                &m_prod,
+                &ut_times_u,
+                &v_times_vt,
+                &init_rand,
+                2,
+            );
+        });
+        return (accepted, values.0, values.1, values.2, values.3);
+    }
+
+    #[test]
+    fn test_pca() {
+        let (_, u, d, v) = pca_example();
+        let (accepted, means, loadings, variances, ratio) = pca_accepts(u, d, v, false);
+        assert!(accepted);
+        for (mean, expected) in means.iter().zip([1.0, 2.0]) {
+            assert!((mean - expected).abs() < 1e-8);
+        }
+        for (loading, expected) in loadings[0].iter().zip([0.6, 0.8]) {
+            assert!((loading - expected).abs() < 1e-8);
+        }
+        assert_eq!(variances.len(), 1);
+        assert!((variances[0] - 16.0 / 3.0).abs() < 1e-7);
+        // 16/(16 + 4) of the total variance is explained by the top component
+        assert!((ratio - 0.8).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_pca_forged_component() {
+        let (_, u, d, _) = pca_example();
+        // the principal directions are swapped, so u*Diag(d)*v is not the centered data
+        let v = vec![vec![0.8, 0.6], vec![-0.6, 0.8]];
+        assert!(!pca_accepts(u, d, v, false).0);
+    }
+
+    #[test]
+    fn test_pca_forged_product() {
+        let (_, u, d, v) = pca_example();
+        assert!(!pca_accepts(u, d, v, true).0);
+    }
+
+    /// Data matrix with 4 samples of 3 features, with negative entries and no constant column
+    fn stats_example() -> Vec<Vec<f64>> {
+        return vec![
+            vec![1.0, 2.0, -0.5],
+            vec![0.5, -1.0, 1.5],
+            vec![2.5, 0.0, 1.0],
+            vec![-1.0, 3.0, 0.5],
+        ];
+    }
+
+    /// Reference `x^T*x` computed in f64
+    fn gram_f64(x: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+        let p = x[0].len();
+        return (0..p)
+            .map(|i| (0..p).map(|j| x.iter().map(|row| row[i] * row[j]).sum()).collect())
+            .collect();
+    }
+
+    /// Reference sample covariance computed in f64
+    fn covariance_f64(x: &Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+        let n = x.len() as f64;
+        let p = x[0].len();
+        let means: Vec<f64> = (0..p).map(|j| x.iter().map(|row| row[j]).sum::<f64>() / n).collect();
+        let x_c: Vec<Vec<f64>> = x
+            .iter()
+            .map(|row| row.iter().zip(means.iter()).map(|(a, m)| a - m).collect())
+            .collect();
+        return gram_f64(&x_c)
+            .iter()
+            .map(|row| row.iter().map(|a| a / (n - 1.0)).collect())
+            .collect();
+    }
+
+    /// Asserts that `a` and `b` have the same dimensions and entries within `tol`
+    fn assert_mat_close(a: &Vec<Vec<f64>>, b: &Vec<Vec<f64>>, tol: f64) {
+        assert_eq!(a.len(), b.len());
+        for (row_a, row_b) in a.iter().zip(b.iter()) {
+            assert_eq!(row_a.len(), row_b.len());
+            for (elem_a, elem_b) in row_a.iter().zip(row_b.iter()) {
+                assert!((elem_a - elem_b).abs() < tol, "{elem_a} != {elem_b}");
+            }
+        }
+    }
+
+    /// Runs both phases of the Gram matrix computation for `x`; if `forge` is set, the last entry of the committed product
+    /// `x^T*x` is forged before the second phase
+    ///
+    /// Outputs whether `MockProver` accepts, along with the dequantized Gram matrix
+    fn gram_accepts(x: Vec<Vec<f64>>, forge: bool) -> (bool, Vec<Vec<f64>>) {
+        let mut gram_dq = Vec::new();
+        let accepted = mock_accepts(|ctx, fpchip| {
+            let x = ZkMatrix::new(ctx, fpchip, &x);
+            let (x_t, xt_times_x, gram) = check_gram_phase0(ctx, fpchip, &x);
+            let xt_times_x = if forge { forge_last_entry(ctx, &xt_times_x) } else { xt_times_x };
+            gram_dq = gram.dequantize(fpchip);
+            let init_rand = mock_init_rand(ctx);
+            check_gram_phase1(ctx, fpchip, &x, &x_t, &xt_times_x, &init_rand, 2);
+        });
+        return (accepted, gram_dq);
+    }
+
+    /// Runs both phases of the covariance computation for `x` followed by `correlation`; if `forge` is set, the last entry of
+    /// the committed product `x_c^T*x_c` is forged before the second phase
+    ///
+    /// Outputs whether `MockProver` accepts, along with the dequantized covariance matrix, standard deviations and correlation matrix
+    fn covariance_accepts(
+        x: Vec<Vec<f64>>,
+        forge: bool,
+    ) -> (bool, Vec<Vec<f64>>, Vec<f64>, Vec<Vec<f64>>) {
+        let mut values = (Vec::new(), Vec::new(), Vec::new());
+        let accepted = mock_accepts(|ctx, fpchip| {
+            let x = ZkMatrix::new(ctx, fpchip, &x);
+            let (_, x_c, x_c_t, xct_times_xc, cov) = check_covariance_phase0(ctx, fpchip, &x, 2);
+            let xct_times_xc =
+                if forge { forge_last_entry(ctx, &xct_times_xc) } else { xct_times_xc };
+            let (std, corr) = correlation(ctx, fpchip, &cov, 4);
+            values = (cov.dequantize(fpchip), std.dequantize(fpchip), corr.dequantize(fpchip));
+            let init_rand = mock_init_rand(ctx);
+            check_covariance_phase1(ctx, fpchip, &x_c, &x_c_t, &xct_times_xc, &init_rand, 2);
+        });
+        return (accepted, values.0, values.1, values.2);
+    }
+
+    #[test]
+    fn test_gram() {
+        let x = stats_example();
+        let (accepted, gram) = gram_accepts(x.clone(), false);
+        assert!(accepted);
+        assert_mat_close(&gram, &gram_f64(&x), 1e-8);
+    }
+
+    #[test]
+    fn test_gram_forged_product() {
+        assert!(!gram_accepts(stats_example(), true).0);
+    }
+
+    #[test]
+    fn test_covariance_and_correlation() {
+        let x = stats_example();
+        let (accepted, cov, std, corr) = covariance_accepts(x.clone(), false);
+        assert!(accepted);
+
+        let cov_ref = covariance_f64(&x);
+        assert_mat_close(&cov, &cov_ref, 1e-8);
+        let std_ref: Vec<f64> = (0..3).map(|i| cov_ref[i][i].sqrt()).collect();
+        assert_mat_close(&vec![std], &vec![std_ref.clone()], 1e-8);
+        let corr_ref: Vec<Vec<f64>> = (0..3)
+            .map(|i| (0..3).map(|j| cov_ref[i][j] / (std_ref[i] * std_ref[j])).collect())
+            .collect();
+        assert_mat_close(&corr, &corr_ref, 1e-7);
+    }
+
+    #[test]
+    fn test_covariance_forged_product() {
+        assert!(!covariance_accepts(stats_example(), true).0);
+    }
+}
@@ -1,10 +1,12 @@
 // #![allow(warnings)]
 #![allow(dead_code)]
 // #[allow(unused_imports)]
-use halo2_base::gates::{GateChip, RangeChip};
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip, RangeInstructions};
 use halo2_base::utils::BigPrimeField;
-use halo2_base::AssignedValue;
-use halo2_base::Context;
+use halo2_base::{
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
 use num_bigint::BigUint;
 use zk_fixed_point_chip::gadget::fixed_point::{FixedPointChip, FixedPointInstructions};
 
@@ -27,7 +29,7 @@ use std::cmp;
 ///
 /// NOTE: the fixed point chip does not check for overflows, one needs to place some bound on $\Vert a \Vert_2$.
 /// Specifically, it should be sufficient to ensure that $m \Vert a \Vert_2 < 2^P$.
-/// This bound is assumed to be enforced by the function or program calling this library
+/// This bound is assumed to be enforced by the function or program calling this library, e.g., with `check_svd_norm_bound`
 pub fn check_svd_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
     ctx: &mut Context<F>,
     fpchip: &FixedPointChip<F, PRECISION_BITS>,
@@ -114,6 +116,51 @@ pub fn check_svd_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
     return (u_t, v_t, m_times_vt, u_times_ut, v_times_vt);
 }
 
+/// Same as `check_svd_phase0`, but returns an error instead of panicking if the inputs do not have the dimensions
+/// required for an SVD, i.e., if a matrix is empty or ragged, `u` or `v` is not square, `u` (`v`) does not have as many rows
+/// as `m` has rows (columns), or `d` does not have length `min{N, M}`
+pub fn try_check_svd_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    err_svd: f64,
+    err_u: f64,
+    max_bits_d: u32,
+) -> Result<
+    (
+        ZkMatrix<F, PRECISION_BITS>,
+        ZkMatrix<F, PRECISION_BITS>,
+        Vec<Vec<AssignedValue<F>>>,
+        Vec<Vec<AssignedValue<F>>>,
+        Vec<Vec<AssignedValue<F>>>,
+    ),
+    MatrixError,
+> {
+    let m_dims = m.checked_dims("m")?;
+    let u_dims = u.checked_dims("u")?;
+    let v_dims = v.checked_dims("v")?;
+    if u_dims.0 != u_dims.1 {
+        return Err(MatrixError::NotSquare { operand: "u", dims: u_dims });
+    }
+    if v_dims.0 != v_dims.1 {
+        return Err(MatrixError::NotSquare { operand: "v", dims: v_dims });
+    }
+    check_mul_dims("check_svd_phase0", ("u", u_dims), ("m", m_dims), None)?;
+    check_mul_dims("check_svd_phase0", ("m", m_dims), ("v", v_dims), None)?;
+    if d.size() != cmp::min(m_dims.0, m_dims.1) {
+        return Err(MatrixError::SingularValueLength {
+            operand: "d",
+            matrix: "m",
+            dims: m_dims,
+            found: d.size(),
+        });
+    }
+    return Ok(check_svd_phase0(ctx, fpchip, m, u, v, d, err_svd, err_u, max_bits_d));
+}
+
 /// Second phase function for checking SVD;
 ///
 /// `check_svd_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
@@ -122,6 +169,10 @@ pub fn check_svd_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
 ///
 /// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
 ///
+/// The three products committed to in the first phase are checked together with `ZkMatrix::verify_mul_batch` using a single
+/// repetition; see `check_svd_phase1_amplified` for more repetitions and `check_svd_phase1_with_mode` to check `m_times_vt`
+/// with a different Freivalds check
+///
 /// First phase might silently fail if `m` is not correctly encoded according to the fixed representation of `fpchip`
 pub fn check_svd_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
     ctx: &mut Context<F>,
@@ -136,12 +187,543 @@ pub fn check_svd_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
     v_times_vt: &Vec<Vec<AssignedValue<F>>>,
     init_rand: &AssignedValue<F>,
 ) {
-    ZkMatrix::verify_mul(ctx, &fpchip, &m, &v_t, &m_times_vt, &init_rand);
-    ZkMatrix::verify_mul(ctx, &fpchip, &u, &u_t, &u_times_ut, &init_rand);
-    ZkMatrix::verify_mul(ctx, &fpchip, &v, &v_t, &v_times_vt, &init_rand);
+    check_svd_phase1_amplified(
+        ctx, fpchip, m, u, v, u_t, v_t, m_times_vt, u_times_ut, v_times_vt, init_rand, 1,
+    );
+}
+
+/// Same as `check_svd_phase1`, but repeats the batched Freivalds check `num_reps` times; a wrong product passes with
+/// probability at most `2^-freivalds_soundness_bits(max(N, M) + 2*N + M - 2, num_reps)` (use `freivalds_num_reps` to choose it)
+pub fn check_svd_phase1_amplified<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    u_times_ut: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(m, v_t, m_times_vt), (u, u_t, u_times_ut), (v, v_t, v_times_vt)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
     // println!("Phase1 success");
 }
 
+/// Same as `check_svd_phase1_amplified`, but checks `m_times_vt` on its own with `ZkMatrix::verify_mul_with_mode` using `mode`
+/// (e.g. `FreivaldsMode::TwoSided` for a tall `m`); the two unitarity products are still batched
+///
+/// `num_reps` is the number of repetitions of each Freivalds check; the degrees are `max(N, M) + N + M - 2` for the batch and
+/// `M - 1` (`RowWise`) or `N + M - 2` (`TwoSided`) for `m_times_vt` (see `freivalds_soundness_bits`)
+pub fn check_svd_phase1_with_mode<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    u_times_ut: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    mode: FreivaldsMode,
+    num_reps: usize,
+) {
+    ZkMatrix::verify_mul_with_mode(ctx, &fpchip, m, v_t, m_times_vt, &init_rand, mode, num_reps);
+    let products = vec![(u, u_t, u_times_ut), (v, v_t, v_times_vt)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Checks that the inputs of `check_svd_phase1` have the dimensions of the inputs and outputs of `check_svd_phase0`
+/// and that `num_reps` is not zero
+fn check_svd_phase1_dims<F: BigPrimeField, const PRECISION_BITS: u32>(
+    op: &'static str,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    u_times_ut: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    num_reps: usize,
+) -> Result<(), MatrixError> {
+    check_num_reps(op, num_reps)?;
+    let m_dims = m.checked_dims("m")?;
+    let u_dims = u.checked_dims("u")?;
+    let v_dims = v.checked_dims("v")?;
+    let u_t_dims = u_t.checked_dims("u_t")?;
+    let v_t_dims = v_t.checked_dims("v_t")?;
+    let m_times_vt_dims = matrix_dims("m_times_vt", m_times_vt)?;
+    let u_times_ut_dims = matrix_dims("u_times_ut", u_times_ut)?;
+    let v_times_vt_dims = matrix_dims("v_times_vt", v_times_vt)?;
+    check_mul_dims(op, ("m", m_dims), ("v_t", v_t_dims), Some(("m_times_vt", m_times_vt_dims)))?;
+    check_mul_dims(op, ("u", u_dims), ("u_t", u_t_dims), Some(("u_times_ut", u_times_ut_dims)))?;
+    check_mul_dims(op, ("v", v_dims), ("v_t", v_t_dims), Some(("v_times_vt", v_times_vt_dims)))?;
+    return Ok(());
+}
+
+/// Same as `check_svd_phase1`, but returns an error instead of panicking if the inputs do not have the dimensions
+/// of the inputs and outputs of `check_svd_phase0`
+pub fn try_check_svd_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    u_times_ut: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+) -> Result<(), MatrixError> {
+    check_svd_phase1_dims(
+        "check_svd_phase1",
+        m,
+        u,
+        v,
+        u_t,
+        v_t,
+        m_times_vt,
+        u_times_ut,
+        v_times_vt,
+        1,
+    )?;
+    check_svd_phase1(ctx, fpchip, m, u, v, u_t, v_t, m_times_vt, u_times_ut, v_times_vt, init_rand);
+    return Ok(());
+}
+
+/// Same as `check_svd_phase1_amplified`, but returns an error instead of panicking if the inputs do not have the dimensions
+/// of the inputs and outputs of `check_svd_phase0` or `num_reps` is zero
+pub fn try_check_svd_phase1_amplified<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    u_times_ut: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) -> Result<(), MatrixError> {
+    check_svd_phase1_dims(
+        "check_svd_phase1_amplified",
+        m,
+        u,
+        v,
+        u_t,
+        v_t,
+        m_times_vt,
+        u_times_ut,
+        v_times_vt,
+        num_reps,
+    )?;
+    check_svd_phase1_amplified(
+        ctx, fpchip, m, u, v, u_t, v_t, m_times_vt, u_times_ut, v_times_vt, init_rand, num_reps,
+    );
+    return Ok(());
+}
+
+/// Same as `check_svd_phase1_with_mode`, but returns an error instead of panicking if the inputs do not have the dimensions
+/// of the inputs and outputs of `check_svd_phase0` or `num_reps` is zero
+pub fn try_check_svd_phase1_with_mode<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    u_times_ut: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    mode: FreivaldsMode,
+    num_reps: usize,
+) -> Result<(), MatrixError> {
+    check_svd_phase1_dims(
+        "check_svd_phase1_with_mode",
+        m,
+        u,
+        v,
+        u_t,
+        v_t,
+        m_times_vt,
+        u_times_ut,
+        v_times_vt,
+        num_reps,
+    )?;
+    check_svd_phase1_with_mode(
+        ctx, fpchip, m, u, v, u_t, v_t, m_times_vt, u_times_ut, v_times_vt, init_rand, mode,
+        num_reps,
+    );
+    return Ok(());
+}
+
+/// Constrains the largest singular value `d[0]` verified by `check_svd_phase0` to be less than `norm_bound_calc(PRECISION_BITS, size, err_svd, err_u)`,
+/// which makes sure that `size*`$\Vert m \Vert_2$` < 2^PRECISION_BITS`, as required to avoid overflows
+///
+/// `size` should be `max{N, M}` and `err_svd`, `err_u` must be the same as the ones used in `check_svd_phase0`; it is called
+/// separately, after `check_svd_phase0`, when the bound is not enforced by the caller
+///
+/// Assumes `d` has been range checked by `check_svd_phase0`; panics (outside of circuit) if the bound is not positive, i.e.,
+/// if the errors are too large for the precision
+pub fn check_svd_norm_bound<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    size: usize,
+    err_svd: f64,
+    err_u: f64,
+) {
+    let bound = norm_bound_calc(PRECISION_BITS, size, err_svd, err_u);
+    assert!(bound > 0.0, "err_svd and err_u are too large to bound the operator norm");
+    let bound_q = BigUint::from((bound * (2u128.pow(PRECISION_BITS) as f64)).floor() as u128);
+    fpchip.range_gate().check_big_less_than_safe(ctx, d.v[0], bound_q);
+}
+
+/// Given matrices `m` (`N X M` dimension), `u` (`N X k` dimension), `v` (`k X M` dimension) and
+/// a vector `d` (`k` dimension) with `k = min{N, M}` in fixed point representation with `fpchip`, performs the first part
+/// of checks that `m = u*d*v` is the thin (economy) SVD of `m`, where the vector `d` is viewed as a diagonal matrix;
+///
+/// `u` has orthonormal columns, `v` has orthonormal rows and `d` is a positive decreasing vector of singular values; only the
+/// orthonormality that holds is checked, i.e., `u^T*u = Id` and `v*v^T = Id`, both of which are `k X k`
+///
+/// Since one of `u` and `v` is square (and hence orthogonal), `m = u*d*v` is checked as `m*v^T = u*d` for `N >= M` and as
+/// `u^T*m = d*v` for `N < M`; the corresponding product is the third output
+///
+/// The other inputs are the same as for `check_svd_phase0`, and `err_svd`, `err_u` are computed with `err_calc` as well
+///
+/// Must call `check_thin_svd_phase1` function following this function in the second phase to complete the check
+///
+/// The outputs are simply witnesses to be used for the corresponding variables in `check_thin_svd_phase1`
+///
+/// NOTE: the same bound on $\Vert m \Vert_2$ as for `check_svd_phase0` is assumed to be enforced by the caller
+pub fn check_thin_svd_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    err_svd: f64,
+    err_u: f64,
+    max_bits_d: u32,
+) -> (
+    ZkMatrix<F, PRECISION_BITS>,
+    ZkMatrix<F, PRECISION_BITS>,
+    Vec<Vec<AssignedValue<F>>>,
+    Vec<Vec<AssignedValue<F>>>,
+    Vec<Vec<AssignedValue<F>>>,
+) {
+    #![allow(non_snake_case)]
+    let N = m.num_rows;
+    let M = m.num_col;
+    let k = cmp::min(N, M);
+    assert_eq!((u.num_rows, u.num_col), (N, k));
+    assert_eq!((v.num_rows, v.num_col), (k, M));
+    assert_eq!(k, d.size());
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    // check the entries of d have at most max_bits_d + precision_bits
+    let max_bits = (max_bits_d + PRECISION_BITS) as usize;
+    d.entries_less_than(ctx, &fpchip, max_bits);
+    // make sure d is in decreasing order
+    d.entries_in_desc_order(ctx, &fpchip, max_bits);
+
+    // check that the entries of u, v correspond to real numbers in the interval (-1.0,1.0) upto an error of 2^-PRECISION_BITS
+    let unit_bnd_q = BigUint::from(2u64.pow(PRECISION_BITS) + 1);
+    check_mat_entries_bounded(ctx, &range, &u.matrix, &unit_bnd_q);
+    check_mat_entries_bounded(ctx, &range, &v.matrix, &unit_bnd_q);
+
+    let u_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&u);
+    let v_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&v);
+
+    // define the doubly scaled errors
+    let err_svd_scale =
+        BigUint::from((err_svd * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+    let err_u_scale =
+        BigUint::from((err_u * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+
+    let m_prod: Vec<Vec<AssignedValue<F>>> = if N >= M {
+        // v is square; m*v^T = u*d is N X M
+        let u_times_d = mat_times_diag_mat(ctx, gate, &u.matrix, &d.v);
+        let m_times_vt = honest_prover_mat_mul(ctx, &m.matrix, &v_t.matrix);
+        check_mat_diff(ctx, &range, &u_times_d, &m_times_vt, &err_svd_scale);
+        m_times_vt
+    } else {
+        // u is square; u^T*m = d*v is N X M
+        let d_times_v = diag_mat_times_mat(ctx, gate, &d.v, &v.matrix);
+        let ut_times_m = honest_prover_mat_mul(ctx, &u_t.matrix, &m.matrix);
+        check_mat_diff(ctx, &range, &d_times_v, &ut_times_m, &err_svd_scale);
+        ut_times_m
+    };
+
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+    let quant_square = ctx.load_constant(quant * quant);
+
+    let ut_times_u = honest_prover_mat_mul(ctx, &u_t.matrix, &u.matrix);
+    check_mat_id(ctx, &range, &ut_times_u, &quant_square, &err_u_scale);
+
+    let v_times_vt = honest_prover_mat_mul(ctx, &v.matrix, &v_t.matrix);
+    check_mat_id(ctx, &range, &v_times_vt, &quant_square, &err_u_scale);
+
+    return (u_t, v_t, m_prod, ut_times_u, v_times_vt);
+}
+
+/// Second phase function for checking the thin SVD;
+///
+/// `check_thin_svd_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `m`, `u`, `v` as used in `check_thin_svd_phase0` and other inputs correspond to the outputs of `check_thin_svd_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// The three products are checked together with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree
+/// `max(N, M) + 3*min(N, M) - 2`; `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_thin_svd_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_prod: &Vec<Vec<AssignedValue<F>>>,
+    ut_times_u: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let m_triple = if m.num_rows >= m.num_col { (m, v_t, m_prod) } else { (u_t, m, m_prod) };
+    let products = vec![m_triple, (u_t, u, ut_times_u), (v, v_t, v_times_vt)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Given matrices `m` (`N X M` dimension), `u` (`N X k` dimension), `v` (`k X M` dimension) and a vector `d` (`k` dimension)
+/// with `k <= min{N, M}` in fixed point representation with `fpchip`, performs the first part of checks that `u`, `d`, `v` are the
+/// top `k` singular triplets of `m` and that `u*d*v` is a good rank `k` approximation of `m`, where `d` is viewed as a diagonal matrix;
+///
+/// Checks that `u` has orthonormal columns (`u^T*u = Id`), `v` has orthonormal rows (`v*v^T = Id`), `m*v^T = u*d` and that `d` is a
+/// positive decreasing vector, with `err_svd`, `err_u` and `max_bits_d` as in `check_svd_phase0`
+///
+/// Also constrains the Frobenius norm of the residual `m - u*d*v` to be less than `residual_bound` (up to rounding, see below)
+/// without ever computing the `N X M` residual: using the committed products, its square is
+/// `|m|_F^2 - 2*sum_i d[i]*(u^T*(m*v^T))[i][i] + sum_{i,j} d[i]*d[j]*(u^T*u)[i][j]*(v*v^T)[j][i]`, which only needs O(N*k + k^2) cells
+/// on top of the O(N*M) cells for `|m|_F^2`
+///
+/// The committed products `m*v^T`, `u^T*u` and `v*v^T` are rescaled once (with `ZkMatrix::rescale_matrix`) before being used in this
+/// formula, which changes the computed square by at most about `2*(sqrt(N) + |d|_1)*|d|_1*2^-PRECISION_BITS`; `residual_bound`
+/// should account for this slack. The square is computed with a scale of `2^(4*PRECISION_BITS)`, so `4*PRECISION_BITS` plus the
+/// number of bits of `|m|_F^2` must be less than the number of bits of the field
+///
+/// Must call `check_truncated_svd_phase1` function following this function in the second phase to complete the check
+///
+/// The outputs are simply witnesses to be used for the corresponding variables in `check_truncated_svd_phase1`
+pub fn check_truncated_svd_phase0<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    err_svd: f64,
+    err_u: f64,
+    max_bits_d: u32,
+    residual_bound: f64,
+) -> (
+    ZkMatrix<F, PRECISION_BITS>,
+    ZkMatrix<F, PRECISION_BITS>,
+    Vec<Vec<AssignedValue<F>>>,
+    Vec<Vec<AssignedValue<F>>>,
+    Vec<Vec<AssignedValue<F>>>,
+) {
+    #![allow(non_snake_case)]
+    let N = m.num_rows;
+    let M = m.num_col;
+    let k = d.size();
+    assert!(k >= 1 && k <= cmp::min(N, M));
+    assert_eq!((u.num_rows, u.num_col), (N, k));
+    assert_eq!((v.num_rows, v.num_col), (k, M));
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    // check the entries of d have at most max_bits_d + precision_bits
+    let max_bits = (max_bits_d + PRECISION_BITS) as usize;
+    d.entries_less_than(ctx, &fpchip, max_bits);
+    // make sure d is in decreasing order
+    d.entries_in_desc_order(ctx, &fpchip, max_bits);
+
+    // check that the entries of u, v correspond to real numbers in the interval (-1.0,1.0) upto an error of 2^-PRECISION_BITS
+    let unit_bnd_q = BigUint::from(2u64.pow(PRECISION_BITS) + 1);
+    check_mat_entries_bounded(ctx, &range, &u.matrix, &unit_bnd_q);
+    check_mat_entries_bounded(ctx, &range, &v.matrix, &unit_bnd_q);
+
+    let u_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&u);
+    let v_t: ZkMatrix<F, PRECISION_BITS> = ZkMatrix::transpose_matrix(&v);
+
+    // define the doubly scaled errors
+    let err_svd_scale =
+        BigUint::from((err_svd * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+    let err_u_scale =
+        BigUint::from((err_u * (2u128.pow(2 * PRECISION_BITS) as f64)).round() as u128);
+
+    let u_times_d = mat_times_diag_mat(ctx, gate, &u.matrix, &d.v);
+    let m_times_vt = honest_prover_mat_mul(ctx, &m.matrix, &v_t.matrix);
+    check_mat_diff(ctx, &range, &u_times_d, &m_times_vt, &err_svd_scale);
+
+    let quant = F::from(2u64.pow(PRECISION_BITS));
+    let quant_square = ctx.load_constant(quant * quant);
+
+    let ut_times_u = honest_prover_mat_mul(ctx, &u_t.matrix, &u.matrix);
+    check_mat_id(ctx, &range, &ut_times_u, &quant_square, &err_u_scale);
+
+    let v_times_vt = honest_prover_mat_mul(ctx, &v.matrix, &v_t.matrix);
+    check_mat_id(ctx, &range, &v_times_vt, &quant_square, &err_u_scale);
+
+    // residual energy; all the terms below are brought to the scale 2^(4*PRECISION_BITS)
+    let mvt = ZkMatrix::rescale_matrix(ctx, fpchip, &m_times_vt);
+    let g = ZkMatrix::rescale_matrix(ctx, fpchip, &ut_times_u);
+    let h = ZkMatrix::rescale_matrix(ctx, fpchip, &v_times_vt);
+
+    // |m|_F^2, scale 2^(2*PRECISION_BITS)
+    let mut m_norm_sq = ctx.load_zero();
+    for row in &m.matrix {
+        let row_norm_sq = gate.inner_product(ctx, row.clone(), row.iter().map(|x| Existing(*x)));
+        m_norm_sq = gate.add(ctx, m_norm_sq, row_norm_sq);
+    }
+
+    // <m, u*d*v> = sum_i d[i]*(u^T*(m*v^T))[i][i], scale 2^(3*PRECISION_BITS)
+    let mut cross = ctx.load_zero();
+    for i in 0..k {
+        let ut_mvt_ii = gate.inner_product(
+            ctx,
+            u_t.matrix[i].clone(),
+            (0..N).map(|n| Existing(mvt.matrix[n][i])),
+        );
+        cross = gate.mul_add(ctx, d.v[i], ut_mvt_ii, cross);
+    }
+
+    // |u*d*v|_F^2 = sum_{i,j} d[i]*d[j]*g[i][j]*h[j][i], scale 2^(4*PRECISION_BITS)
+    let mut approx_norm_sq = ctx.load_zero();
+    for i in 0..k {
+        let mut g_h_d: Vec<AssignedValue<F>> = Vec::new();
+        for j in 0..k {
+            let g_h = gate.mul(ctx, g.matrix[i][j], h.matrix[j][i]);
+            g_h_d.push(gate.mul(ctx, g_h, d.v[j]));
+        }
+        let row_sum = gate.sum(ctx, g_h_d);
+        approx_norm_sq = gate.mul_add(ctx, d.v[i], row_sum, approx_norm_sq);
+    }
+
+    let energy = gate.mul_add(ctx, m_norm_sq, Constant(quant * quant), approx_norm_sq);
+    let energy = gate.mul_add(ctx, cross, Constant(-quant - quant), energy);
+
+    // |energy| < residual_bound^2, where the bound is quantized with the scale 2^(4*PRECISION_BITS)
+    let bnd_sq_q = BigUint::from(
+        (residual_bound * residual_bound * (2u128.pow(PRECISION_BITS) as f64)).round() as u128,
+    ) * BigUint::from(2u32).pow(3 * PRECISION_BITS);
+    check_abs_less_than(ctx, &range, energy, &bnd_sq_q);
+
+    return (u_t, v_t, m_times_vt, ut_times_u, v_times_vt);
+}
+
+/// Second phase function for checking the truncated SVD;
+///
+/// `check_truncated_svd_phase0` should be run in the first phase, so that its outputs are commited to in the first phase;
+///
+/// Inputs correspond to the `m`, `u`, `v` as used in `check_truncated_svd_phase0` and other inputs correspond to the outputs of
+/// `check_truncated_svd_phase0`
+///
+/// `init_rand` is the random challenge created after the first phase; must be a commitment of all the inputs to this function
+///
+/// The three products are checked together with `ZkMatrix::verify_mul_batch`, whose polynomial identity has degree `N + 3*k - 2`;
+/// `num_reps` is the number of repetitions (see `freivalds_num_reps`)
+pub fn check_truncated_svd_phase1<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    m: &ZkMatrix<F, PRECISION_BITS>,
+    u: &ZkMatrix<F, PRECISION_BITS>,
+    v: &ZkMatrix<F, PRECISION_BITS>,
+    u_t: &ZkMatrix<F, PRECISION_BITS>,
+    v_t: &ZkMatrix<F, PRECISION_BITS>,
+    m_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    ut_times_u: &Vec<Vec<AssignedValue<F>>>,
+    v_times_vt: &Vec<Vec<AssignedValue<F>>>,
+    init_rand: &AssignedValue<F>,
+    num_reps: usize,
+) {
+    let products = vec![(m, v_t, m_times_vt), (u_t, u, ut_times_u), (v, v_t, v_times_vt)];
+    ZkMatrix::verify_mul_batch(ctx, &fpchip, &products, &init_rand, num_reps);
+}
+
+/// Given the vector of singular values `d` verified by `check_svd_phase0` and a threshold `tau` in fixed point representation
+/// with `fpchip`, outputs the number of singular values that are strictly greater than `tau`, i.e., the numerical rank of `m`
+/// at threshold `tau`
+///
+/// `tau` can be a witness or a constant (load it with `ctx.load_constant(fpchip.quantization(tau))`); it is constrained to be in
+/// `[0, 2^(max_bits_d + PRECISION_BITS))`, where `max_bits_d` is the same as the one used in `check_svd_phase0`
+///
+/// The output is an integer (*not* fixed point encoded) and can be exposed as a public instance to prove that the rank of `m`
+/// is at most (or at least) `k`
+///
+/// Uses one comparison per entry of `d` along with the constraint that the comparisons switch from 1 to 0 at most once,
+/// which holds for sorted `d`
+///
+/// Asserts (outside the circuit) that `d` is not empty
+pub fn check_numerical_rank<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+    tau: AssignedValue<F>,
+    max_bits_d: u32,
+) -> AssignedValue<F> {
+    assert!(d.size() > 0);
+
+    let range: &RangeChip<F> = fpchip.range_gate();
+    let gate: &GateChip<F> = fpchip.gate();
+
+    let max_bits = (max_bits_d + PRECISION_BITS) as usize;
+    range.range_check(ctx, tau, max_bits);
+
+    let mut above_tau: Vec<AssignedValue<F>> = Vec::new();
+    for elem in &d.v {
+        above_tau.push(range.is_less_than(ctx, tau, *elem, max_bits));
+    }
+    // monotone switch point: above_tau[i+1] = 1 implies above_tau[i] = 1
+    for i in 0..(above_tau.len() - 1) {
+        let switch_up = gate.mul_not(ctx, above_tau[i], above_tau[i + 1]);
+        gate.assert_is_const(ctx, &switch_up, &F::zero());
+    }
+    let rank = gate.sum(ctx, above_tau);
+    return rank;
+}
+
+/// With zk constraints calculates the nuclear norm `d[0] + ... + d[K-1]` from a vector of singular values `d`
+///
+/// Only sums the entries of `d`; nothing ties `d` to a matrix, so `d` must be the vector of singular values verified by
+/// `check_svd_phase0` and `check_svd_phase1` (or the thin SVD check), which also constrains its entries to be non-negative
+///
+/// Leads to about `K` constraints
+pub fn nuclear_norm<F: BigPrimeField, const PRECISION_BITS: u32>(
+    ctx: &mut Context<F>,
+    fpchip: &FixedPointChip<F, PRECISION_BITS>,
+    d: &ZkVector<F, PRECISION_BITS>,
+) -> AssignedValue<F> {
+    return fpchip.gate().sum(ctx, d.v.clone());
+}
+
 /// Calculates `err_svd` and `err_u` from `eps_svd` and `eps_u` -- see Eq. 21, 22, and 23 of notes on error analysis for an explanation
 ///
 /// `p` is the PRECISION_BITS for the fixed point chip
@@ -160,3 +742,363 @@ pub fn err_calc(p: u32, size: usize, max_norm: f64, eps_svd: f64, eps_u: f64) ->
     let err_u = eps_u + precision * (size as f64) * (2.0 * (1.0 + eps_u) + precision);
     return (err_svd, err_u);
 }
+
+/// Calculates the largest value of `d[0]` allowed by `check_svd_norm_bound`
+///
+/// `p` is the PRECISION_BITS for the fixed point chip, `size` is `max{N, M}` and `err_svd`, `err_u` are the outputs of `err_calc`
+///
+/// Since `m*v^T = u*d` up to `err_svd` in each entry and $\Vert u^T u - I \Vert_2, \Vert v v^T - I \Vert_2 \leq$ `size*err_u`,
+/// we have $\Vert m \Vert_2 \leq$ `(sqrt(1 + size*err_u)*d[0] + size*err_svd)/sqrt(1 - size*err_u)`; the output is the value of
+/// `d[0]` for which this bound is `2^p/size`
+pub fn norm_bound_calc(p: u32, size: usize, err_svd: f64, err_u: f64) -> f64 {
+    let size = size as f64;
+    let max_norm = 2.0_f64.powf(p as f64) / size;
+    let bound =
+        (max_norm * (1.0 - size * err_u).sqrt() - size * err_svd) / (1.0 + size * err_u).sqrt();
+    return bound;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::test_utils::*;
+    use halo2_base::gates::builder::GateThreadBuilder;
+
+    /// Which of the products committed to by `check_svd_phase0` a malicious prover forges before the second phase
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Forge {
+        Honest,
+        MTimesVt,
+        UTimesUt,
+        VTimesVt,
+    }
+
+    /// Householder reflection `I - 2*w*w^T/(w^T*w)`; this is an orthogonal matrix
+    fn householder(w: &Vec<f64>) -> Vec<Vec<f64>> {
+        let norm_sq: f64 = w.iter().map(|x| x * x).sum();
+        let mut h: Vec<Vec<f64>> = Vec::new();
+        for i in 0..w.len() {
+            let mut row: Vec<f64> = Vec::new();
+            for j in 0..w.len() {
+                let id = if i == j { 1.0 } else { 0.0 };
+                row.push(id - 2.0 * w[i] * w[j] / norm_sq);
+            }
+            h.push(row);
+        }
+        return h;
+    }
+
+    /// Returns `(m, u, v, d)` such that `m = u*Diag(d)*v` for a dense `4 X 3` matrix `m`
+    fn svd_example() -> (Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<f64>) {
+        let u = householder(&vec![1.0, -2.0, 0.5, 3.0]);
+        let v = householder(&vec![0.3, 1.0, -1.5]);
+        let d = vec![3.0, 1.5, 0.5];
+
+        let mut m: Vec<Vec<f64>> = Vec::new();
+        for i in 0..u.len() {
+            let mut row: Vec<f64> = Vec::new();
+            for j in 0..v.len() {
+                let mut elem = 0.0;
+                for k in 0..d.len() {
+                    elem += u[i][k] * d[k] * v[k][j];
+                }
+                row.push(elem);
+            }
+            m.push(row);
+        }
+        return (m, u, v, d);
+    }
+
+    /// Checks the SVD of `svd_example` with `check_svd_phase0` and `check_svd_phase1_amplified` with `num_reps` repetitions
+    /// (`check_svd_phase1` for one repetition, or `check_svd_phase1_with_mode` if `mode` is set), where the product selected by
+    /// `forge` has its last entry shifted by one in the field (far below the tolerance of the first phase) before the second phase
+    fn svd_accepts(forge: Forge, mode: Option<FreivaldsMode>, num_reps: usize) -> bool {
+        let (m, u, v, d) = svd_example();
+        return mock_accepts(|ctx, fpchip| {
+            let m = ZkMatrix::new(ctx, fpchip, &m);
+            let u = ZkMatrix::new(ctx, fpchip, &u);
+            let v = ZkMatrix::new(ctx, fpchip, &v);
+            let d = ZkVector::new(ctx, fpchip, &d);
+            let (err_svd, err_u) = err_calc(PRECISION_BITS, 4, 4.0, 0.0, 0.0);
+            let (u_t, v_t, m_times_vt, u_times_ut, v_times_vt) =
+                check_svd_phase0(ctx, fpchip, &m, &u, &v, &d, err_svd, err_u, 2);
+            let m_times_vt = if forge == Forge::MTimesVt {
+                forge_last_entry(ctx, &m_times_vt)
+            } else {
+                m_times_vt
+            };
+            let u_times_ut = if forge == Forge::UTimesUt {
+                forge_last_entry(ctx, &u_times_ut)
+            } else {
+                u_times_ut
+            };
+            let v_times_vt = if forge == Forge::VTimesVt {
+                forge_last_entry(ctx, &v_times_vt)
+            } else {
+                v_times_vt
+            };
+            let init_rand = mock_init_rand(ctx);
+            match mode {
+                None if num_reps == 1 => check_svd_phase1(
+                    ctx,
+                    fpchip,
+                    &m,
+                    &u,
+                    &v,
+                    &u_t,
+                    &v_t,
+                    &m_times_vt,
+                    &u_times_ut,
+                    &v_times_vt,
+                    &init_rand,
+                ),
+                None => check_svd_phase1_amplified(
+                    ctx,
+                    fpchip,
+                    &m,
+                    &u,
+                    &v,
+                    &u_t,
+                    &v_t,
+                    &m_times_vt,
+                    &u_times_ut,
+                    &v_times_vt,
+                    &init_rand,
+                    num_reps,
+                ),
+                Some(mode) => check_svd_phase1_with_mode(
+                    ctx,
+                    fpchip,
+                    &m,
+                    &u,
+                    &v,
+                    &u_t,
+                    &v_t,
+                    &m_times_vt,
+                    &u_times_ut,
+                    &v_times_vt,
+                    &init_rand,
+                    mode,
+                    num_reps,
+                ),
+            }
+        });
+    }
+
+    #[test]
+    fn test_svd_honest() {
+        assert!(svd_accepts(Forge::Honest, None, 2));
+    }
+
+    #[test]
+    fn test_svd_single_repetition() {
+        assert!(svd_accepts(Forge::Honest, None, 1));
+        for forge in [Forge::MTimesVt, Forge::UTimesUt, Forge::VTimesVt] {
+            assert!(!svd_accepts(forge, None, 1), "forged {:?} was accepted", forge);
+        }
+    }
+
+    #[test]
+    fn test_svd_forged_products() {
+        for forge in [Forge::MTimesVt, Forge::UTimesUt, Forge::VTimesVt] {
+            assert!(!svd_accepts(forge, None, 2), "forged {:?} was accepted", forge);
+        }
+    }
+
+    #[test]
+    fn test_svd_two_sided() {
+        assert!(svd_accepts(Forge::Honest, Some(FreivaldsMode::TwoSided), 2));
+        assert!(svd_accepts(Forge::Honest, Some(FreivaldsMode::RowWise), 2));
+    }
+
+    #[test]
+    fn test_svd_two_sided_forged_products() {
+        for forge in [Forge::MTimesVt, Forge::UTimesUt, Forge::VTimesVt] {
+            let accepted = svd_accepts(forge, Some(FreivaldsMode::TwoSided), 2);
+            assert!(!accepted, "forged {:?} was accepted", forge);
+        }
+    }
+
+    /// Checks `u*d*v` as the thin SVD of `m`
+    fn thin_svd_accepts(m: Vec<Vec<f64>>, u: Vec<Vec<f64>>, d: Vec<f64>, v: Vec<Vec<f64>>) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let m = ZkMatrix::new(ctx, fpchip, &m);
+            let u = ZkMatrix::new(ctx, fpchip, &u);
+            let d = ZkVector::new(ctx, fpchip, &d);
+            let v = ZkMatrix::new(ctx, fpchip, &v);
+            let (err_svd, err_u) = err_calc(PRECISION_BITS, 3, 3.0, 0.0, 0.0);
+            let (u_t, v_t, m_prod, ut_times_u, v_times_vt) =
+                check_thin_svd_phase0(ctx, fpchip, &m, &u, &v, &d, err_svd, err_u, 2);
+            let init_rand = mock_init_rand(ctx);
+            check_thin_svd_phase1(
+                ctx,
+                fpchip,
+                &m,
+                &u,
+                &v,
+                &u_t,
+                &v_t,
+                &m_prod,
+                &ut_times_u,
+                &v_times_vt,
+                &init_rand,
+                2,
+            );
+        });
+    }
+
+    /// `m = u*Diag(d)*v` for the `3 X 2` matrix `m` used in the thin SVD tests
+    fn thin_svd_example() -> (Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<f64>, Vec<Vec<f64>>) {
+        let m = vec![vec![0.0, 1.8], vec![0.0, 2.4], vec![2.0, 0.0]];
+        let u = vec![vec![0.6, 0.0], vec![0.8, 0.0], vec![0.0, 1.0]];
+        let d = vec![3.0, 2.0];
+        let v = vec![vec![0.0, 1.0], vec![1.0, 0.0]];
+        return (m, u, d, v);
+    }
+
+    #[test]
+    fn test_thin_svd_honest() {
+        let (m, u, d, v) = thin_svd_example();
+        assert!(thin_svd_accepts(m.clone(), u.clone(), d.clone(), v.clone()));
+        // N < M: the SVD of the transpose
+        let m_t = (0..2).map(|j| m.iter().map(|row| row[j]).collect()).collect();
+        let v_t = (0..2).map(|j| u.iter().map(|row| row[j]).collect()).collect();
+        assert!(thin_svd_accepts(m_t, v, d, v_t));
+    }
+
+    #[test]
+    fn test_thin_svd_wrong_singular_value() {
+        let (m, u, _, v) = thin_svd_example();
+        assert!(!thin_svd_accepts(m, u, vec![3.0, 1.9], v));
+    }
+
+    #[test]
+    fn test_thin_svd_not_orthonormal() {
+        // m*v^T = u*d still holds after scaling the first column of u by 1.25 and the first singular value by 1/1.25
+        let (m, _, _, v) = thin_svd_example();
+        let u = vec![vec![0.75, 0.0], vec![1.0, 0.0], vec![0.0, 1.0]];
+        assert!(!thin_svd_accepts(m, u, vec![2.4, 2.0], v));
+    }
+
+    /// Checks `u*d*v` as the top singular triplet of the `3 X 2` matrix of the thin SVD tests, whose residual has norm 2
+    fn truncated_svd_accepts(
+        u: Vec<Vec<f64>>,
+        d: Vec<f64>,
+        v: Vec<Vec<f64>>,
+        residual_bound: f64,
+    ) -> bool {
+        let (m, _, _, _) = thin_svd_example();
+        return mock_accepts(|ctx, fpchip| {
+            let m = ZkMatrix::new(ctx, fpchip, &m);
+            let u = ZkMatrix::new(ctx, fpchip, &u);
+            let d = ZkVector::new(ctx, fpchip, &d);
+            let v = ZkMatrix::new(ctx, fpchip, &v);
+            let (err_svd, err_u) = err_calc(PRECISION_BITS, 3, 3.0, 0.0, 0.0);
+            let (u_t, v_t, m_times_vt, ut_times_u, v_times_vt) = check_truncated_svd_phase0(
+                ctx,
+                fpchip,
+                &m,
+                &u,
+                &v,
+                &d,
+                err_svd,
+                err_u,
+                2,
+                residual_bound,
+            );
+            let init_rand = mock_init_rand(ctx);
+            check_truncated_svd_phase1(
+                ctx,
+                fpchip,
+                &m,
+                &u,
+                &v,
+                &u_t,
+                &v_t,
+                &m_times_vt,
+                &ut_times_u,
+                &v_times_vt,
+                &init_rand,
+                2,
+            );
+        });
+    }
+
+    #[test]
+    fn test_truncated_svd_honest() {
+        let u = vec![vec![0.6], vec![0.8], vec![0.0]];
+        assert!(truncated_svd_accepts(u, vec![3.0], vec![vec![0.0, 1.0]], 2.01));
+    }
+
+    #[test]
+    fn test_truncated_svd_residual_too_large() {
+        let u = vec![vec![0.6], vec![0.8], vec![0.0]];
+        assert!(!truncated_svd_accepts(u, vec![3.0], vec![vec![0.0, 1.0]], 1.9));
+    }
+
+    #[test]
+    fn test_truncated_svd_wrong_singular_value() {
+        let u = vec![vec![0.6], vec![0.8], vec![0.0]];
+        assert!(!truncated_svd_accepts(u, vec![2.9], vec![vec![0.0, 1.0]], 2.1));
+    }
+
+    /// Checks that `check_numerical_rank` of `d` at threshold `tau` is `rank`
+    fn rank_accepts(d: Vec<f64>, tau: f64, rank: u64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let d = ZkVector::new(ctx, fpchip, &d);
+            let tau = ctx.load_constant(fpchip.quantization(tau));
+            let out = check_numerical_rank(ctx, fpchip, &d, tau, 3);
+            fpchip.gate().assert_is_const(ctx, &out, &Fr::from(rank));
+        });
+    }
+
+    #[test]
+    fn test_numerical_rank() {
+        assert!(rank_accepts(vec![3.0, 1.0, 0.01], 0.1, 2));
+        assert!(rank_accepts(vec![3.0, 1.0, 0.01], 4.0, 0));
+        // only singular values strictly greater than tau are counted
+        assert!(rank_accepts(vec![3.0, 1.0, 0.01], 1.0, 1));
+    }
+
+    #[test]
+    fn test_numerical_rank_wrong_claim() {
+        assert!(!rank_accepts(vec![3.0, 1.0, 0.01], 0.1, 3));
+        assert!(!rank_accepts(vec![3.0, 1.0, 0.01], 1.0, 2));
+    }
+
+    /// Checks `check_svd_norm_bound` for singular values `[d0, 1]`
+    fn norm_bound_accepts(d0: f64, size: usize, err_svd: f64, err_u: f64) -> bool {
+        return mock_accepts(|ctx, fpchip| {
+            let d = ZkVector::new(ctx, fpchip, &vec![d0, 1.0]);
+            check_svd_norm_bound(ctx, fpchip, &d, size, err_svd, err_u);
+        });
+    }
+
+    #[test]
+    fn test_svd_norm_bound() {
+        // with no errors the bound on d[0] is exactly 2^PRECISION_BITS/size
+        assert_eq!(norm_bound_calc(PRECISION_BITS, 4, 0.0, 0.0), 2.0_f64.powi(30));
+        let (err_svd, err_u) = err_calc(PRECISION_BITS, 4, 4.0, 1e-6, 1e-6);
+        let bound = norm_bound_calc(PRECISION_BITS, 4, err_svd, err_u);
+        assert!(bound < 2.0_f64.powi(30));
+        assert!(norm_bound_accepts(bound - 2.0_f64.powi(-10), 4, err_svd, err_u));
+        assert!(!norm_bound_accepts(bound + 2.0_f64.powi(-10), 4, err_svd, err_u));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_numerical_rank_empty() {
+        let mut builder = GateThreadBuilder::<Fr>::new(false);
+        let ctx = builder.main(0);
+        let fpchip = FixedPointChip::<Fr, PRECISION_BITS>::default(12);
+        let d: ZkVector<Fr, PRECISION_BITS> = ZkVector { v: vec![] };
+        let tau = ctx.load_constant(fpchip.quantization(0.1));
+        check_numerical_rank(ctx, &fpchip, &d, tau, 3);
+    }
+
+    #[test]
+    fn test_numerical_rank_unsorted() {
+        // the comparisons switch from 0 back to 1, which is only possible for unsorted d
+        assert!(!rank_accepts(vec![0.01, 3.0, 1.0], 0.5, 2));
+    }
+}